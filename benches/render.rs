@@ -0,0 +1,28 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use i3_tree_manager::bench::{balanced_tree, row_cells, row_ids};
+
+/// Containers in the synthetic trees, from a busy desktop up
+const SIZES: &[usize] = &[100, 1_000, 10_000];
+/// Children of each container
+const FANOUT: usize = 4;
+/// Name column of a full width terminal
+const NAME_WIDTH: usize = 80;
+
+fn render_path(c: &mut Criterion) {
+    let mut group = c.benchmark_group("render");
+    for size in SIZES {
+        let tree = balanced_tree(*size, FANOUT);
+        group.throughput(Throughput::Elements(*size as u64));
+        group.bench_with_input(BenchmarkId::new("collect_ids", size), &tree, |b, tree| {
+            b.iter(|| row_ids(black_box(tree)))
+        });
+        group.bench_with_input(BenchmarkId::new("ui_rows", size), &tree, |b, tree| {
+            b.iter(|| row_cells(black_box(tree), NAME_WIDTH))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, render_path);
+criterion_main!(benches);
@@ -0,0 +1,278 @@
+use std::{env, error::Error};
+
+use i3ipc::{
+    reply::{CommandOutcome, Node, Workspace},
+    I3Connection,
+};
+
+use crate::ipc::{NodeExtras, RawConnection};
+
+/// Window manager connection the tree is read from and commands are sent to
+pub(crate) trait TreeBackend {
+    fn get_tree(&mut self) -> Result<Node, Box<dyn Error>>;
+
+    /// The tree along with the properties `Node` leaves out, none of them
+    /// by default
+    fn get_tree_with_extras(&mut self) -> Result<(Node, NodeExtras), Box<dyn Error>> {
+        Ok((self.get_tree()?, NodeExtras::new()))
+    }
+
+    fn get_workspaces(&mut self) -> Result<Vec<Workspace>, Box<dyn Error>>;
+
+    /// Whether the `gaps` command is known whatever the config says
+    fn supports_gaps(&self) -> bool {
+        false
+    }
+
+    /// Outcome of each of the `;` separated commands
+    fn run_command(&mut self, command: &str) -> Result<Vec<CommandOutcome>, Box<dyn Error>>;
+}
+
+/// Backend reading the tree from the raw replies, properties `i3ipc` leaves
+/// out included
+struct WithExtras {
+    backend: Box<dyn TreeBackend>,
+    raw_port: RawConnection,
+}
+
+impl TreeBackend for WithExtras {
+    fn get_tree(&mut self) -> Result<Node, Box<dyn Error>> {
+        Ok(self.raw_port.get_tree()?.0)
+    }
+
+    fn get_tree_with_extras(&mut self) -> Result<(Node, NodeExtras), Box<dyn Error>> {
+        Ok(self.raw_port.get_tree()?)
+    }
+
+    fn get_workspaces(&mut self) -> Result<Vec<Workspace>, Box<dyn Error>> {
+        self.backend.get_workspaces()
+    }
+
+    fn supports_gaps(&self) -> bool {
+        self.backend.supports_gaps()
+    }
+
+    fn run_command(&mut self, command: &str) -> Result<Vec<CommandOutcome>, Box<dyn Error>> {
+        self.backend.run_command(command)
+    }
+}
+
+impl TreeBackend for I3Connection {
+    fn get_tree(&mut self) -> Result<Node, Box<dyn Error>> {
+        Ok(I3Connection::get_tree(self)?)
+    }
+
+    fn get_workspaces(&mut self) -> Result<Vec<Workspace>, Box<dyn Error>> {
+        Ok(I3Connection::get_workspaces(self)?.workspaces)
+    }
+
+    fn run_command(&mut self, command: &str) -> Result<Vec<CommandOutcome>, Box<dyn Error>> {
+        Ok(I3Connection::run_command(self, command)?.outcomes)
+    }
+}
+
+/// Make every connection, event listeners included, go through the socket
+/// at `path`. `i3ipc` has no constructor taking a path but reads `I3SOCK`
+/// first, as `RawConnection` does, so this must run before any thread starts
+pub(crate) fn use_socket(path: &str) {
+    env::set_var("I3SOCK", path);
+    if env::var_os("SWAYSOCK").is_some() {
+        env::set_var("SWAYSOCK", path);
+    }
+}
+
+/// Talk to Sway when it runs and support for it is compiled in, to i3 otherwise
+pub(crate) fn connect() -> Result<Box<dyn TreeBackend>, Box<dyn Error>> {
+    let backend = connect_wm()?;
+    let raw_port = RawConnection::connect()?;
+    Ok(Box::new(WithExtras { backend, raw_port }))
+}
+
+fn connect_wm() -> Result<Box<dyn TreeBackend>, Box<dyn Error>> {
+    #[cfg(feature = "sway")]
+    {
+        if std::env::var_os("SWAYSOCK").is_some() {
+            return Ok(Box::new(swayipc::Connection::new()?));
+        }
+    }
+    Ok(Box::new(I3Connection::connect()?))
+}
+
+/// Fixed tree, recording the commands it is sent
+#[cfg(test)]
+pub(crate) mod mock {
+    use std::{cell::RefCell, error::Error, rc::Rc};
+
+    use i3ipc::reply::{CommandOutcome, Node, NodeType, Workspace};
+
+    use super::TreeBackend;
+
+    /// Workspaces of the subtree of `node`, the first one focused
+    fn workspaces(node: &Node, workspaces: &mut Vec<Workspace>) {
+        if node.nodetype == NodeType::Workspace {
+            workspaces.push(Workspace {
+                num: -1,
+                name: node.name.clone().unwrap_or_default(),
+                visible: workspaces.is_empty(),
+                focused: workspaces.is_empty(),
+                urgent: node.urgent,
+                rect: node.rect,
+                output: String::new(),
+            });
+        }
+        for child in &node.nodes {
+            self::workspaces(child, workspaces);
+        }
+    }
+
+    pub(crate) struct MockBackend {
+        pub(crate) tree: Node,
+        pub(crate) commands: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl MockBackend {
+        pub(crate) fn new(tree: Node) -> Self {
+            Self {
+                tree,
+                commands: Rc::default(),
+            }
+        }
+    }
+
+    impl TreeBackend for MockBackend {
+        fn get_tree(&mut self) -> Result<Node, Box<dyn Error>> {
+            Ok(self.tree.clone())
+        }
+
+        fn get_workspaces(&mut self) -> Result<Vec<Workspace>, Box<dyn Error>> {
+            let mut found = vec![];
+            workspaces(&self.tree, &mut found);
+            Ok(found)
+        }
+
+        fn run_command(&mut self, command: &str) -> Result<Vec<CommandOutcome>, Box<dyn Error>> {
+            self.commands.borrow_mut().push(command.to_string());
+            Ok(vec![CommandOutcome {
+                success: true,
+                error: None,
+            }])
+        }
+    }
+}
+
+#[cfg(feature = "sway")]
+mod sway {
+    use std::{collections::HashMap, error::Error};
+
+    use i3ipc::reply::{
+        CommandOutcome, Node, NodeBorder, NodeLayout, NodeType, WindowProperty, Workspace,
+    };
+    use swayipc::{Connection, Rect};
+
+    use super::TreeBackend;
+
+    impl TreeBackend for Connection {
+        fn get_tree(&mut self) -> Result<Node, Box<dyn Error>> {
+            Ok(into_i3_node(Connection::get_tree(self)?))
+        }
+
+        fn get_workspaces(&mut self) -> Result<Vec<Workspace>, Box<dyn Error>> {
+            let workspaces = Connection::get_workspaces(self)?
+                .into_iter()
+                .map(|workspace| Workspace {
+                    num: workspace.num,
+                    name: workspace.name,
+                    visible: workspace.visible,
+                    focused: workspace.focused,
+                    urgent: workspace.urgent,
+                    rect: into_rect(workspace.rect),
+                    output: workspace.output,
+                })
+                .collect();
+            Ok(workspaces)
+        }
+
+        fn supports_gaps(&self) -> bool {
+            true
+        }
+
+        fn run_command(&mut self, command: &str) -> Result<Vec<CommandOutcome>, Box<dyn Error>> {
+            let outcomes = Connection::run_command(self, command)?
+                .into_iter()
+                .map(|outcome| CommandOutcome {
+                    success: outcome.is_ok(),
+                    error: outcome.err().map(|err| err.to_string()),
+                })
+                .collect();
+            Ok(outcomes)
+        }
+    }
+
+    fn into_rect(rect: Rect) -> (i32, i32, i32, i32) {
+        (rect.x, rect.y, rect.width, rect.height)
+    }
+
+    /// Sway replies are a superset of i3 ones, keep what `i3ipc` knows about
+    fn into_i3_node(node: swayipc::Node) -> Node {
+        let window_properties = node.window_properties.map(|properties| {
+            let mut i3_properties = HashMap::new();
+            let known = vec![
+                (WindowProperty::Title, properties.title),
+                (WindowProperty::Instance, properties.instance),
+                (WindowProperty::Class, properties.class),
+                (WindowProperty::WindowRole, properties.window_role),
+                (
+                    WindowProperty::TransientFor,
+                    properties.transient_for.map(|id| id.to_string()),
+                ),
+            ];
+            for (property, value) in known {
+                if let Some(value) = value {
+                    i3_properties.insert(property, value);
+                }
+            }
+            i3_properties
+        });
+        Node {
+            focus: node.focus,
+            nodes: node.nodes.into_iter().map(into_i3_node).collect(),
+            floating_nodes: node.floating_nodes.into_iter().map(into_i3_node).collect(),
+            id: node.id,
+            name: node.name,
+            nodetype: match node.node_type {
+                swayipc::NodeType::Root => NodeType::Root,
+                swayipc::NodeType::Output => NodeType::Output,
+                swayipc::NodeType::Workspace => NodeType::Workspace,
+                swayipc::NodeType::Con => NodeType::Con,
+                swayipc::NodeType::FloatingCon => NodeType::FloatingCon,
+                swayipc::NodeType::Dockarea => NodeType::DockArea,
+                _ => NodeType::Unknown,
+            },
+            border: match node.border {
+                swayipc::NodeBorder::Normal => NodeBorder::Normal,
+                swayipc::NodeBorder::Pixel => NodeBorder::Pixel,
+                swayipc::NodeBorder::None => NodeBorder::None,
+                _ => NodeBorder::Unknown,
+            },
+            current_border_width: node.current_border_width,
+            layout: match node.layout {
+                swayipc::NodeLayout::SplitH => NodeLayout::SplitH,
+                swayipc::NodeLayout::SplitV => NodeLayout::SplitV,
+                swayipc::NodeLayout::Stacked => NodeLayout::Stacked,
+                swayipc::NodeLayout::Tabbed => NodeLayout::Tabbed,
+                swayipc::NodeLayout::Output => NodeLayout::Output,
+                swayipc::NodeLayout::Dockarea => NodeLayout::DockArea,
+                _ => NodeLayout::Unknown,
+            },
+            percent: node.percent,
+            rect: into_rect(node.rect),
+            window_rect: into_rect(node.window_rect),
+            deco_rect: into_rect(node.deco_rect),
+            geometry: into_rect(node.geometry),
+            window: node.window.map(|window| window as i32),
+            window_properties,
+            urgent: node.urgent,
+            focused: node.focused,
+        }
+    }
+}
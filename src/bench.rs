@@ -0,0 +1,88 @@
+//! Synthetic trees and the entry points of the render path, for the
+//! benchmarks and the unit tests
+
+use std::collections::VecDeque;
+
+use i3ipc::reply::{Node, NodeBorder, NodeLayout, NodeType};
+
+use crate::{collect_ids, ui, NodeId};
+
+/// Named tiled container with the given children
+pub(crate) fn node(id: NodeId, nodes: Vec<Node>) -> Node {
+    Node {
+        focus: vec![],
+        nodes,
+        floating_nodes: vec![],
+        id,
+        name: Some(format!("node {}", id)),
+        nodetype: NodeType::Con,
+        border: NodeBorder::Normal,
+        current_border_width: 2,
+        layout: NodeLayout::SplitH,
+        percent: None,
+        rect: (0, 0, 0, 0),
+        window_rect: (0, 0, 0, 0),
+        deco_rect: (0, 0, 0, 0),
+        geometry: (0, 0, 0, 0),
+        window: None,
+        window_properties: None,
+        urgent: false,
+        focused: false,
+    }
+}
+
+/// Tree of `count` containers, numbered from 1 breadth first, each of them
+/// but the last ones holding `fanout` children
+pub fn balanced_tree(count: usize, fanout: usize) -> Node {
+    // Children of each container, by con_id
+    let mut children: Vec<Vec<NodeId>> = vec![vec![]; count + 1];
+    let mut parents: VecDeque<NodeId> = VecDeque::new();
+    parents.push_back(1);
+    let mut next = 2;
+    while let Some(parent) = parents.pop_front() {
+        for _ in 0..fanout {
+            if next > count as NodeId {
+                break;
+            }
+            children[parent as usize].push(next);
+            parents.push_back(next);
+            next += 1;
+        }
+    }
+    fn build(id: NodeId, children: &[Vec<NodeId>]) -> Node {
+        let nodes = children[id as usize]
+            .iter()
+            .map(|child| build(*child, children))
+            .collect();
+        node(id, nodes)
+    }
+    build(1, &children)
+}
+
+/// Ids of the rows of `tree`, nothing folded
+pub fn row_ids(tree: &Node) -> Vec<NodeId> {
+    collect_ids(tree, &Default::default())
+}
+
+/// Cells of the rows of `tree` as drawn with the default config in a name
+/// column of `name_width`
+pub fn row_cells(tree: &Node, name_width: usize) -> Vec<[String; 4]> {
+    ui::plain_rows(tree, name_width)
+        .into_iter()
+        .map(|(cells, ..)| cells)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn balanced_trees_have_the_requested_size() {
+        let tree = balanced_tree(10, 3);
+        assert_eq!(row_ids(&tree), vec![1, 2, 5, 6, 7, 3, 8, 9, 10, 4]);
+        assert_eq!(tree.nodes.len(), 3);
+        assert_eq!(row_cells(&tree, 40).len(), 10);
+        assert_eq!(row_ids(&balanced_tree(1, 3)), vec![1]);
+    }
+}
@@ -0,0 +1,173 @@
+use std::time::Duration;
+
+use i3ipc::Subscription;
+
+/// Command line options
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Args {
+    /// Print the tree to stdout and exit instead of running the TUI
+    pub(crate) print_tree: bool,
+    /// IPC socket to use instead of the one `$I3SOCK` or i3 point to
+    pub(crate) socket: Option<String>,
+    /// File IPC traffic is logged to
+    pub(crate) log: Option<String>,
+    /// Time between two ticks, redrawing the clock and retrying the connection
+    pub(crate) tick_rate: Option<Duration>,
+    /// i3 events the tree is fetched again after, by their i3 name
+    pub(crate) subscribe: Option<Vec<String>>,
+    /// Show and log the commands instead of sending them
+    pub(crate) dry_run: bool,
+    /// Highlight with text colors and modifiers, no background fills
+    pub(crate) no_bg: bool,
+    pub(crate) help: bool,
+}
+
+pub(crate) static USAGE: &str = "\
+Usage: i3_tree_manager [OPTIONS]
+
+Options:
+    --print-tree       print the tree and exit
+    --socket <PATH>    talk to the i3 IPC socket at PATH
+    --log <FILE>       log commands, i3 events and tree refreshes to FILE,
+                       at the $RUST_LOG level, debug by default
+    --tick-rate-ms <MS>
+                       tick every MS milliseconds, 250 by default
+    --subscribe <EVENTS>
+                       fetch the tree after the comma separated i3 EVENTS,
+                       among window, workspace, output, mode, binding and
+                       barconfig_update, window,workspace,output,mode by default
+    --dry-run          show the i3 commands instead of running them
+    --no-bg            color the text of focused and urgent containers rather
+                       than their background, for light terminals
+    -h, --help         print this help";
+
+/// Event subscription `name` stands for, as i3 calls them in its replies
+pub(crate) fn subscription(name: &str) -> Option<Subscription> {
+    match name {
+        "window" => Some(Subscription::Window),
+        "workspace" => Some(Subscription::Workspace),
+        "output" => Some(Subscription::Output),
+        "mode" => Some(Subscription::Mode),
+        "binding" => Some(Subscription::Binding),
+        "barconfig_update" => Some(Subscription::BarConfig),
+        _ => None,
+    }
+}
+
+fn parse_tick_rate(millis: &str) -> Result<Duration, String> {
+    match millis.parse() {
+        Ok(millis) if millis > 0 => Ok(Duration::from_millis(millis)),
+        _ => Err(format!(
+            "--tick-rate-ms takes a number of milliseconds above 0, not {:?}",
+            millis
+        )),
+    }
+}
+
+fn parse_subscribe(list: &str) -> Result<Vec<String>, String> {
+    list.split(',')
+        .map(|name| match subscription(name) {
+            Some(_) => Ok(name.to_string()),
+            None => Err(format!("unknown i3 event {:?} for --subscribe", name)),
+        })
+        .collect()
+}
+
+impl Args {
+    /// Parse the arguments following the program name
+    pub(crate) fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--print-tree" => parsed.print_tree = true,
+                "--socket" => match args.next() {
+                    Some(path) => parsed.socket = Some(path),
+                    None => return Err(format!("--socket takes a path\n\n{}", USAGE)),
+                },
+                "--log" => match args.next() {
+                    Some(path) => parsed.log = Some(path),
+                    None => return Err(format!("--log takes a file\n\n{}", USAGE)),
+                },
+                "--tick-rate-ms" => match args.next() {
+                    Some(millis) => parsed.tick_rate = Some(parse_tick_rate(&millis)?),
+                    None => return Err(format!("--tick-rate-ms takes a number\n\n{}", USAGE)),
+                },
+                "--subscribe" => match args.next() {
+                    Some(list) => parsed.subscribe = Some(parse_subscribe(&list)?),
+                    None => return Err(format!("--subscribe takes events\n\n{}", USAGE)),
+                },
+                "--dry-run" => parsed.dry_run = true,
+                "--no-bg" => parsed.no_bg = true,
+                "-h" | "--help" => parsed.help = true,
+                other => match other.strip_prefix("--socket=") {
+                    Some(path) => parsed.socket = Some(path.to_string()),
+                    None => return Err(format!("unknown argument {:?}\n\n{}", other, USAGE)),
+                },
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn no_arguments_runs_the_tui() {
+        assert_eq!(parse(&[]), Ok(Args::default()));
+    }
+
+    #[test]
+    fn print_tree_flag() {
+        assert!(parse(&["--print-tree"]).unwrap().print_tree);
+    }
+
+    #[test]
+    fn dry_run_flag() {
+        assert!(parse(&["--dry-run"]).unwrap().dry_run);
+        assert!(parse(&["--no-bg", "--dry-run"]).unwrap().no_bg);
+    }
+
+    #[test]
+    fn socket_takes_a_path() {
+        let socket = Some("/tmp/i3.sock".to_string());
+        assert_eq!(parse(&["--socket", "/tmp/i3.sock"]).unwrap().socket, socket);
+        assert_eq!(parse(&["--socket=/tmp/i3.sock"]).unwrap().socket, socket);
+        assert!(parse(&["--socket"]).is_err());
+    }
+
+    #[test]
+    fn log_takes_a_file() {
+        let args = parse(&["--log", "/tmp/i3.log", "--print-tree"]).unwrap();
+        assert_eq!(args.log.as_deref(), Some("/tmp/i3.log"));
+        assert!(args.print_tree);
+        assert!(parse(&["--log"]).is_err());
+    }
+
+    #[test]
+    fn tick_rate_and_subscriptions_are_checked() {
+        let args = parse(&["--tick-rate-ms", "100", "--subscribe", "window,mode"]).unwrap();
+        assert_eq!(args.tick_rate, Some(Duration::from_millis(100)));
+        assert_eq!(
+            args.subscribe,
+            Some(vec!["window".to_string(), "mode".to_string()])
+        );
+        assert!(parse(&["--tick-rate-ms", "0"]).is_err());
+        assert!(parse(&["--tick-rate-ms", "fast"]).is_err());
+        assert_eq!(
+            parse(&["--subscribe", "window,windw"]),
+            Err(r#"unknown i3 event "windw" for --subscribe"#.to_string())
+        );
+    }
+
+    #[test]
+    fn unknown_arguments_are_rejected() {
+        assert!(parse(&["--frobnicate"]).is_err());
+    }
+}
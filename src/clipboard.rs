@@ -0,0 +1,47 @@
+use std::{
+    env,
+    io::{self, Write},
+    process::{Command, Stdio},
+};
+
+/// Tool writing its stdin to the clipboard of the current session
+fn clipboard_command(wayland: bool) -> (&'static str, &'static [&'static str]) {
+    if wayland {
+        ("wl-copy", &[])
+    } else {
+        ("xclip", &["-selection", "clipboard"])
+    }
+}
+
+/// Put `text` in the system clipboard with `wl-copy` on Wayland, `xclip` on X11
+pub(crate) fn copy(text: &str) -> io::Result<()> {
+    let (program, args) = clipboard_command(env::var_os("WAYLAND_DISPLAY").is_some());
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", program, err)))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    if !child.wait()?.success() {
+        return Err(io::Error::other(format!("{} didn't return 0", program)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_the_tool_of_the_session() {
+        assert_eq!(clipboard_command(true).0, "wl-copy");
+        assert_eq!(
+            clipboard_command(false),
+            ("xclip", &["-selection", "clipboard"][..])
+        );
+    }
+}
@@ -0,0 +1,749 @@
+use std::{collections::HashMap, env, error::Error, fs, io, path::PathBuf, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use termion::event::Key;
+use tui::style::Color;
+
+/// Commands that can be bound to a key in select mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Action {
+    Quit,
+    SelectNext,
+    SelectPrevious,
+    SelectFirst,
+    SelectLast,
+    Focus,
+    Search,
+    NextMatch,
+    PreviousMatch,
+    ClearSearch,
+    MoveMode,
+    ResizeMode,
+    SplitToggle,
+    SplitHorizontal,
+    SplitVertical,
+    CycleLayout,
+    CycleBorder,
+    FullscreenToggle,
+    FloatingToggle,
+    Kill,
+    ExportJson,
+    ExportDot,
+    CopyId,
+    Command,
+    Mark,
+    FollowFocus,
+    LiveFocus,
+    SelectFocused,
+    NextUrgent,
+    SelectParent,
+    SelectFirstChild,
+    Swap,
+    MoveToWorkspace,
+    Details,
+    ShowClass,
+    Clock,
+    Collapse,
+    CollapseAll,
+    ExpandAll,
+    ScratchpadShow,
+    Jump,
+    MoveToScratchpad,
+    Workspaces,
+    Reload,
+    Undo,
+    CycleOutput,
+    WorkspaceOnly,
+    Compact,
+    Counts,
+    Marks,
+    GapsMode,
+    LayoutMode,
+    RawJson,
+    Help,
+}
+
+impl Action {
+    pub(crate) const ALL: &'static [Action] = &[
+        Action::Help,
+        Action::SelectNext,
+        Action::SelectPrevious,
+        Action::SelectFirst,
+        Action::SelectLast,
+        Action::SelectParent,
+        Action::SelectFirstChild,
+        Action::Focus,
+        Action::Search,
+        Action::NextMatch,
+        Action::PreviousMatch,
+        Action::ClearSearch,
+        Action::NextUrgent,
+        Action::MoveMode,
+        Action::ResizeMode,
+        Action::GapsMode,
+        Action::LayoutMode,
+        Action::Swap,
+        Action::Undo,
+        Action::MoveToWorkspace,
+        Action::SplitToggle,
+        Action::SplitHorizontal,
+        Action::SplitVertical,
+        Action::CycleLayout,
+        Action::CycleBorder,
+        Action::FullscreenToggle,
+        Action::FloatingToggle,
+        Action::Kill,
+        Action::ExportJson,
+        Action::ExportDot,
+        Action::CopyId,
+        Action::Command,
+        Action::Details,
+        Action::RawJson,
+        Action::ShowClass,
+        Action::Counts,
+        Action::CycleOutput,
+        Action::WorkspaceOnly,
+        Action::Compact,
+        Action::Clock,
+        Action::Collapse,
+        Action::CollapseAll,
+        Action::ExpandAll,
+        Action::ScratchpadShow,
+        Action::Jump,
+        Action::MoveToScratchpad,
+        Action::Workspaces,
+        Action::Marks,
+        Action::Reload,
+        Action::Mark,
+        Action::FollowFocus,
+        Action::LiveFocus,
+        Action::SelectFocused,
+        Action::Quit,
+    ];
+
+    pub(crate) fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::SelectNext => "select next",
+            Action::SelectPrevious => "select previous",
+            Action::SelectFirst => "select first",
+            Action::SelectLast => "select last",
+            Action::Focus => "focus selection",
+            Action::Search => "search by name",
+            Action::NextMatch => "next match",
+            Action::PreviousMatch => "previous match",
+            Action::ClearSearch => "clear search",
+            Action::MoveMode => "move mode",
+            Action::ResizeMode => "resize mode",
+            Action::SplitToggle => "toggle split",
+            Action::SplitHorizontal => "split horizontally",
+            Action::SplitVertical => "split vertically",
+            Action::CycleLayout => "cycle layout",
+            Action::CycleBorder => "cycle border",
+            Action::FullscreenToggle => "toggle fullscreen",
+            Action::FloatingToggle => "toggle floating",
+            Action::Kill => "kill container",
+            Action::ExportJson => "export tree to JSON",
+            Action::ExportDot => "export tree to Graphviz DOT",
+            Action::CopyId => "copy the con_id to the clipboard",
+            Action::Command => "run an i3 command",
+            Action::Mark => "mark container",
+            Action::FollowFocus => "toggle following i3 focus",
+            Action::LiveFocus => "toggle focusing the selection",
+            Action::SelectFocused => "select the focused container",
+            Action::NextUrgent => "next urgent window",
+            Action::SelectParent => "select parent",
+            Action::SelectFirstChild => "select first child",
+            Action::Swap => "swap with another container",
+            Action::MoveToWorkspace => "move to workspace number",
+            Action::Details => "toggle the details pane",
+            Action::ShowClass => "label windows by class or title",
+            Action::Clock => "toggle the clock and event counter",
+            Action::Collapse => "fold or unfold the selected container",
+            Action::CollapseAll => "fold every workspace",
+            Action::ExpandAll => "unfold everything",
+            Action::ScratchpadShow => "show or hide the scratchpad window",
+            Action::Jump => "jump to a row by its label",
+            Action::MoveToScratchpad => "move to the scratchpad",
+            Action::Workspaces => "pick a workspace to switch to",
+            Action::Reload => "reload the config file",
+            Action::Undo => "undo the last move or swap",
+            Action::CycleOutput => "show one output after the other, then all",
+            Action::WorkspaceOnly => "show the focused workspace alone",
+            Action::Compact => "show workspaces and windows alone",
+            Action::Counts => "show the children count and depth of containers",
+            Action::Marks => "pick a mark to select its container",
+            Action::GapsMode => "gaps mode",
+            Action::LayoutMode => "layout tools mode",
+            Action::RawJson => "show the selection as JSON",
+            Action::Help => "show this help",
+        }
+    }
+}
+
+static DEFAULT_BINDINGS: &[(Action, &[Key])] = &[
+    (Action::Quit, &[Key::Char('q')]),
+    (Action::SelectNext, &[Key::Char('j'), Key::Down]),
+    (Action::SelectPrevious, &[Key::Char('k'), Key::Up]),
+    (Action::SelectFirst, &[Key::Char('g'), Key::Home]),
+    (Action::SelectLast, &[Key::Char('G'), Key::End]),
+    (Action::Focus, &[Key::Char('\n')]),
+    (Action::Search, &[Key::Char('/')]),
+    (Action::NextMatch, &[Key::Char('n')]),
+    (Action::PreviousMatch, &[Key::Char('N')]),
+    (Action::ClearSearch, &[Key::Esc]),
+    (Action::MoveMode, &[Key::Char('m')]),
+    (Action::ResizeMode, &[Key::Char('r')]),
+    (Action::SplitToggle, &[Key::Char('s')]),
+    (Action::SplitHorizontal, &[Key::Char('b')]),
+    (Action::SplitVertical, &[Key::Char('v')]),
+    (Action::CycleLayout, &[Key::Char('c')]),
+    (Action::CycleBorder, &[Key::Char('B')]),
+    (Action::FullscreenToggle, &[Key::Char('f')]),
+    (Action::FloatingToggle, &[Key::Char('o')]),
+    (Action::Kill, &[Key::Char('x')]),
+    (Action::ExportJson, &[Key::Char('e')]),
+    (Action::ExportDot, &[Key::Char('E')]),
+    (Action::CopyId, &[Key::Char('y')]),
+    (Action::Command, &[Key::Char(':')]),
+    (Action::Mark, &[Key::Char('M')]),
+    (Action::FollowFocus, &[Key::Char('F')]),
+    (Action::LiveFocus, &[Key::Char('V')]),
+    (Action::SelectFocused, &[Key::Char('.')]),
+    (Action::NextUrgent, &[Key::Char('u')]),
+    (Action::SelectParent, &[Key::Char('h'), Key::Left]),
+    (Action::SelectFirstChild, &[Key::Char('l'), Key::Right]),
+    (Action::Swap, &[Key::Char('S')]),
+    (Action::MoveToWorkspace, &[Key::Char('t')]),
+    (Action::Details, &[Key::Char('i')]),
+    (Action::ShowClass, &[Key::Char('a')]),
+    (Action::Clock, &[Key::Char('T')]),
+    (Action::Collapse, &[Key::Char(' ')]),
+    (Action::CollapseAll, &[Key::Char('-')]),
+    (Action::ExpandAll, &[Key::Char('+')]),
+    (Action::ScratchpadShow, &[Key::Char('p')]),
+    (Action::Jump, &[Key::Char('L')]),
+    (Action::MoveToScratchpad, &[Key::Char('P')]),
+    (Action::Workspaces, &[Key::Char('w')]),
+    (Action::Reload, &[Key::Ctrl('r')]),
+    (Action::Undo, &[Key::Char('U')]),
+    (Action::CycleOutput, &[Key::Char('O')]),
+    (Action::WorkspaceOnly, &[Key::Char('W')]),
+    (Action::Compact, &[Key::Char('C')]),
+    (Action::Counts, &[Key::Char('#')]),
+    (Action::Marks, &[Key::Char('\'')]),
+    (Action::GapsMode, &[Key::Char('z')]),
+    (Action::LayoutMode, &[Key::Char('=')]),
+    (Action::RawJson, &[Key::Char('J')]),
+    (Action::Help, &[Key::Char('?')]),
+];
+
+/// Keys bound to each action, as written in the config file
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+enum KeyNames {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyNames {
+    fn names(&self) -> &[String] {
+        match self {
+            KeyNames::One(name) => std::slice::from_ref(name),
+            KeyNames::Many(names) => names,
+        }
+    }
+}
+
+/// Switches of the `[options]` table
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Options {
+    /// Render outputs as header rows showing their resolution
+    pub(crate) output_headers: bool,
+    /// Select the first container after the last one and the other way around
+    pub(crate) wrap_navigation: bool,
+    /// Draw with ASCII characters only, guessed from the locale when unset
+    pub(crate) ascii: Option<bool>,
+    /// i3 handles gaps, as i3-gaps and i3 4.22 on do, Sway always does
+    pub(crate) gaps: bool,
+    /// Seconds status messages stay, `STATUS_TIMEOUT` when unset and for
+    /// good with 0
+    pub(crate) status_timeout: Option<u64>,
+}
+
+/// Seconds status messages stay by default
+const STATUS_TIMEOUT: u64 = 5;
+
+impl Options {
+    /// How long status messages stay, `None` when they never clear
+    pub(crate) fn status_timeout(&self) -> Option<Duration> {
+        match self.status_timeout.unwrap_or(STATUS_TIMEOUT) {
+            0 => None,
+            seconds => Some(Duration::from_secs(seconds)),
+        }
+    }
+}
+
+/// Colors of the `[theme]` table as written, unset ones keep their default
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+struct ThemeNames {
+    focused: Option<String>,
+    urgent: Option<String>,
+    selected: Option<String>,
+    output: Option<String>,
+    workspace: Option<String>,
+    window: Option<String>,
+    floating: Option<String>,
+}
+
+/// Colors of the tree
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Theme {
+    /// Background of the container focused by i3
+    pub(crate) focused: Color,
+    /// Background of urgent windows
+    pub(crate) urgent: Color,
+    /// Background of the selected row, which is reversed when unset
+    pub(crate) selected: Option<Color>,
+    pub(crate) output: Color,
+    pub(crate) workspace: Color,
+    pub(crate) window: Color,
+    /// Foreground of whatever lives in a floating container
+    pub(crate) floating: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            focused: Color::LightGreen,
+            urgent: Color::LightMagenta,
+            selected: None,
+            output: Color::Blue,
+            workspace: Color::Yellow,
+            window: Color::White,
+            floating: Color::Cyan,
+        }
+    }
+}
+
+impl Theme {
+    fn from_names(names: ThemeNames) -> Result<Self, String> {
+        let default = Self::default();
+        let color = |name: Option<String>, default: Color| {
+            name.map_or(Ok(default), |name| parse_color(&name))
+        };
+        Ok(Self {
+            focused: color(names.focused, default.focused)?,
+            urgent: color(names.urgent, default.urgent)?,
+            selected: names.selected.map(|name| parse_color(&name)).transpose()?,
+            output: color(names.output, default.output)?,
+            workspace: color(names.workspace, default.workspace)?,
+            window: color(names.window, default.window)?,
+            floating: color(names.floating, default.floating)?,
+        })
+    }
+
+    #[cfg(test)]
+    fn names(&self) -> ThemeNames {
+        ThemeNames {
+            focused: Some(color_name(self.focused)),
+            urgent: Some(color_name(self.urgent)),
+            selected: self.selected.map(color_name),
+            output: Some(color_name(self.output)),
+            workspace: Some(color_name(self.workspace)),
+            window: Some(color_name(self.window)),
+            floating: Some(color_name(self.floating)),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct ConfigFile {
+    /// Keyed by action name, enum keys aren't supported by `toml`
+    #[serde(default)]
+    keys: HashMap<String, KeyNames>,
+    #[serde(default)]
+    options: Options,
+    #[serde(default)]
+    theme: ThemeNames,
+}
+
+/// Content of `config.toml`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Config {
+    pub(crate) keymap: Keymap,
+    pub(crate) options: Options,
+    pub(crate) theme: Theme,
+}
+
+impl Config {
+    /// Load `config.toml`, falling back to the defaults when there is none
+    pub(crate) fn load() -> Result<Self, Box<dyn Error>> {
+        match fs::read_to_string(config_path()) {
+            Ok(content) => Self::from_toml(&content),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub(crate) fn from_toml(content: &str) -> Result<Self, Box<dyn Error>> {
+        let file: ConfigFile = toml::from_str(content)?;
+        Ok(Self {
+            keymap: Keymap::from_names(file.keys)?,
+            options: file.options,
+            theme: Theme::from_names(file.theme)?,
+        })
+    }
+
+    #[cfg(test)]
+    pub(crate) fn to_toml(&self) -> Result<String, Box<dyn Error>> {
+        let file = ConfigFile {
+            keys: self.keymap.names()?,
+            options: self.options.clone(),
+            theme: self.theme.names(),
+        };
+        Ok(toml::to_string(&file)?)
+    }
+}
+
+/// Key bindings of select mode
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Keymap {
+    bindings: HashMap<Action, Vec<Key>>,
+    /// Action of each bound key, the reverse of `bindings`
+    actions: HashMap<Key, Action>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::from_bindings(
+            DEFAULT_BINDINGS
+                .iter()
+                .map(|(action, keys)| (*action, keys.to_vec()))
+                .collect(),
+        )
+    }
+}
+
+impl Keymap {
+    fn from_bindings(bindings: HashMap<Action, Vec<Key>>) -> Self {
+        let actions = bindings
+            .iter()
+            .flat_map(|(action, keys)| keys.iter().map(move |key| (*key, *action)))
+            .collect();
+        Self { bindings, actions }
+    }
+
+    /// Bindings of the config override the default ones action by action,
+    /// and take their keys away from the default ones. A key the config
+    /// binds twice is an error
+    fn from_names(keys: HashMap<String, KeyNames>) -> Result<Self, Box<dyn Error>> {
+        let mut bindings = Self::default().bindings;
+        let mut bound: HashMap<Key, Action> = HashMap::new();
+        for (action, names) in keys {
+            let action: Action = toml::Value::String(action).try_into()?;
+            let keys: Vec<Key> = names
+                .names()
+                .iter()
+                .map(|name| parse_key(name))
+                .collect::<Result<_, _>>()?;
+            for key in &keys {
+                if let Some(other) = bound.insert(*key, action) {
+                    return Err(format!(
+                        "{} is bound to both {:?} and {:?}",
+                        key_name(*key),
+                        other,
+                        action
+                    )
+                    .into());
+                }
+            }
+            bindings.insert(action, keys);
+        }
+        for (action, keys) in bindings.iter_mut() {
+            keys.retain(|key| bound.get(key).is_none_or(|owner| owner == action));
+        }
+        Ok(Self::from_bindings(bindings))
+    }
+
+    #[cfg(test)]
+    fn names(&self) -> Result<HashMap<String, KeyNames>, Box<dyn Error>> {
+        let mut keys = HashMap::new();
+        for (action, bound) in &self.bindings {
+            let action = toml::Value::try_from(action)?
+                .as_str()
+                .unwrap_or_default()
+                .to_string();
+            let names = bound.iter().map(|key| key_name(*key)).collect();
+            keys.insert(action, KeyNames::Many(names));
+        }
+        Ok(keys)
+    }
+
+    pub(crate) fn action(&self, key: Key) -> Option<Action> {
+        self.actions.get(&key).copied()
+    }
+
+    pub(crate) fn keys(&self, action: Action) -> &[Key] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Name of the first key bound to `action`, for menus
+    pub(crate) fn label(&self, action: Action) -> String {
+        self.keys(action)
+            .first()
+            .map_or_else(|| "-".to_string(), |key| key_name(*key))
+    }
+}
+
+fn config_path() -> PathBuf {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_default()
+        .join("i3_tree_manager")
+        .join("config.toml")
+}
+
+static NAMED_KEYS: &[(&str, Key)] = &[
+    ("ENTER", Key::Char('\n')),
+    ("TAB", Key::Char('\t')),
+    ("SPACE", Key::Char(' ')),
+    ("ESC", Key::Esc),
+    ("BACKSPACE", Key::Backspace),
+    ("DELETE", Key::Delete),
+    ("INSERT", Key::Insert),
+    ("UP", Key::Up),
+    ("DOWN", Key::Down),
+    ("LEFT", Key::Left),
+    ("RIGHT", Key::Right),
+    ("HOME", Key::Home),
+    ("END", Key::End),
+    ("PAGEUP", Key::PageUp),
+    ("PAGEDOWN", Key::PageDown),
+    ("BACKTAB", Key::BackTab),
+];
+
+/// Parse names such as `j`, `Down`, `Ctrl-r`, `Alt-x` or `F5`
+pub(crate) fn parse_key(name: &str) -> Result<Key, String> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(Key::Char(c));
+    }
+    let upper = name.to_uppercase();
+    if let Some((_, key)) = NAMED_KEYS.iter().find(|(named, _)| *named == upper) {
+        return Ok(*key);
+    }
+    let modified = |prefix: &str| {
+        let rest = name.get(prefix.len()..)?;
+        let mut chars = rest.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if upper.starts_with(prefix) => Some(c),
+            _ => None,
+        }
+    };
+    if let Some(c) = modified("CTRL-") {
+        return Ok(Key::Ctrl(c));
+    }
+    if let Some(c) = modified("ALT-") {
+        return Ok(Key::Alt(c));
+    }
+    match upper.strip_prefix('F').map(str::parse) {
+        Some(Ok(n)) if (1..=12).contains(&n) => Ok(Key::F(n)),
+        _ => Err(format!("unknown key {:?}", name)),
+    }
+}
+
+/// Inverse of `parse_key`
+pub(crate) fn key_name(key: Key) -> String {
+    if let Some((name, _)) = NAMED_KEYS.iter().find(|(_, named)| *named == key) {
+        return name.to_string();
+    }
+    match key {
+        Key::Char(c) => c.to_string(),
+        Key::Ctrl(c) => format!("CTRL-{}", c),
+        Key::Alt(c) => format!("ALT-{}", c),
+        Key::F(n) => format!("F{}", n),
+        other => format!("{:?}", other),
+    }
+}
+
+static NAMED_COLORS: &[(&str, Color)] = &[
+    ("reset", Color::Reset),
+    ("black", Color::Black),
+    ("red", Color::Red),
+    ("green", Color::Green),
+    ("yellow", Color::Yellow),
+    ("blue", Color::Blue),
+    ("magenta", Color::Magenta),
+    ("cyan", Color::Cyan),
+    ("gray", Color::Gray),
+    ("dark_gray", Color::DarkGray),
+    ("light_red", Color::LightRed),
+    ("light_green", Color::LightGreen),
+    ("light_yellow", Color::LightYellow),
+    ("light_blue", Color::LightBlue),
+    ("light_magenta", Color::LightMagenta),
+    ("light_cyan", Color::LightCyan),
+    ("white", Color::White),
+];
+
+/// Color from its name, such as `light_green` or `LightGreen`, a `#rrggbb`
+/// triplet or a 256 colors palette index
+pub(crate) fn parse_color(name: &str) -> Result<Color, String> {
+    let unknown = || format!("unknown color {:?}", name);
+    if let Some(hex) = name.strip_prefix('#') {
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+        };
+        return match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Color::Rgb(r, g, b)),
+            _ => Err(unknown()),
+        };
+    }
+    if let Ok(index) = name.parse() {
+        return Ok(Color::Indexed(index));
+    }
+    let flat = |name: &str| name.to_lowercase().replace(['_', '-'], "");
+    NAMED_COLORS
+        .iter()
+        .find(|(named, _)| flat(named) == flat(name))
+        .map(|(_, color)| *color)
+        .ok_or_else(unknown)
+}
+
+/// Inverse of `parse_color`
+#[cfg(test)]
+fn color_name(color: Color) -> String {
+    if let Some((name, _)) = NAMED_COLORS.iter().find(|(_, named)| *named == color) {
+        return name.to_string();
+    }
+    match color {
+        Color::Rgb(r, g, b) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        Color::Indexed(index) => index.to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static SAMPLE: &str = r#"
+[keys]
+quit = "Esc"
+select_next = ["j", "Down", "Ctrl-n"]
+move_mode = "F5"
+
+[options]
+output_headers = true
+"#;
+
+    #[test]
+    fn sample_config_overrides_defaults() {
+        let config = Config::from_toml(SAMPLE).unwrap();
+        assert!(config.options.output_headers);
+        let keymap = config.keymap;
+        assert_eq!(keymap.keys(Action::Quit), &[Key::Esc]);
+        assert_eq!(
+            keymap.keys(Action::SelectNext),
+            &[Key::Char('j'), Key::Down, Key::Ctrl('n')]
+        );
+        assert_eq!(keymap.action(Key::F(5)), Some(Action::MoveMode));
+        assert_eq!(keymap.action(Key::Char('s')), Some(Action::SplitToggle));
+        // Taken away from the default binding of clear_search
+        assert_eq!(keymap.action(Key::Esc), Some(Action::Quit));
+        assert!(!keymap.keys(Action::ClearSearch).contains(&Key::Esc));
+    }
+
+    #[test]
+    fn default_bindings_share_no_key() {
+        let mut seen = HashMap::new();
+        for (action, keys) in DEFAULT_BINDINGS {
+            for key in *keys {
+                assert_eq!(seen.insert(*key, *action), None, "{:?} is bound twice", key);
+            }
+        }
+    }
+
+    #[test]
+    fn keys_bound_twice_are_rejected() {
+        assert!(Config::from_toml("[keys]\nquit = \"q\"\nfocus = [\"Enter\", \"q\"]").is_err());
+    }
+
+    #[test]
+    fn sample_config_round_trips() {
+        let config = Config::from_toml(SAMPLE).unwrap();
+        let reloaded = Config::from_toml(&config.to_toml().unwrap()).unwrap();
+        assert_eq!(reloaded, config);
+    }
+
+    #[test]
+    fn every_action_has_a_default_binding() {
+        let keymap = Keymap::default();
+        for action in Action::ALL {
+            assert!(!keymap.keys(*action).is_empty(), "{:?} is unbound", action);
+        }
+        assert_eq!(Action::ALL.len(), DEFAULT_BINDINGS.len());
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(Config::from_toml("[keys]\nquit = \"Hyper-q\"").is_err());
+        assert!(Config::from_toml("[keys]\nteleport = \"t\"").is_err());
+    }
+
+    #[test]
+    fn options_default_to_off() {
+        assert_eq!(Config::from_toml("").unwrap().options, Options::default());
+        assert!(Config::from_toml("[options]\nteleport = true").is_err());
+    }
+
+    #[test]
+    fn status_timeout_defaults_and_zero_keeps_messages() {
+        let options = |toml: &str| Config::from_toml(toml).unwrap().options;
+        assert_eq!(
+            options("").status_timeout(),
+            Some(Duration::from_secs(STATUS_TIMEOUT))
+        );
+        assert_eq!(
+            options("[options]\nstatus_timeout = 2").status_timeout(),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            options("[options]\nstatus_timeout = 0").status_timeout(),
+            None
+        );
+    }
+
+    #[test]
+    fn theme_colors_are_parsed() {
+        let config = Config::from_toml(
+            r##"
+[theme]
+focused = "#1e90ff"
+urgent = "LightRed"
+selected = "dark-gray"
+window = "208"
+"##,
+        )
+        .unwrap();
+        let theme = config.theme;
+        assert_eq!(theme.focused, Color::Rgb(0x1e, 0x90, 0xff));
+        assert_eq!(theme.urgent, Color::LightRed);
+        assert_eq!(theme.selected, Some(Color::DarkGray));
+        assert_eq!(theme.window, Color::Indexed(208));
+        assert_eq!(theme.workspace, Theme::default().workspace);
+    }
+
+    #[test]
+    fn unknown_colors_are_rejected() {
+        assert!(parse_color("#12345").is_err());
+        assert!(parse_color("#zz0000").is_err());
+        assert!(Config::from_toml("[theme]\nfocused = \"mauve\"").is_err());
+    }
+}
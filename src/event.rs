@@ -2,33 +2,88 @@ use std::{
     io,
     sync::{
         atomic::{AtomicBool, Ordering},
-        mpsc, Arc,
+        mpsc, Arc, Mutex,
     },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
-use i3ipc::{I3EventListener, Subscription};
-use termion::{event::Key, input::TermRead};
+use i3ipc::{event::Event as I3Event, I3EventListener, Subscription};
+use termion::{
+    event::{Event as TermEvent, Key, MouseEvent},
+    input::TermRead,
+    terminal_size,
+};
+
+/// How often the terminal size is checked, short enough to redraw promptly
+const RESIZE_POLL_RATE: Duration = Duration::from_millis(50);
+/// Pause before the first attempt to reach i3 again once its socket closed
+const RECONNECT_DELAY: Duration = Duration::from_millis(500);
+/// Longest pause between attempts, the pause doubles up to it
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(8);
 
 pub enum Event<I> {
     Input(I),
+    /// Press, release or hold of a mouse button, at 1 based coordinates
+    Mouse(MouseEvent),
     Tick,
     I3,
+    /// Name of the binding mode i3 switched to
+    Mode(String),
+    /// The i3 event stream ended, it is being reconnected
+    I3Disconnected,
+    /// i3 events come in again, the tree may have changed in between
+    I3Reconnected,
+    /// New terminal width and height
+    Resize(u16, u16),
+}
+
+/// Key ending the input thread, unless ignored while text is typed
+#[derive(Clone)]
+struct ExitKey {
+    /// Changes when the config is reloaded
+    key: Arc<Mutex<Key>>,
+    ignored: Arc<AtomicBool>,
+}
+
+impl ExitKey {
+    fn new(key: Key) -> Self {
+        Self {
+            key: Arc::new(Mutex::new(key)),
+            ignored: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    fn matches(&self, key: Key) -> bool {
+        !self.ignored.load(Ordering::Relaxed)
+            && self.key.lock().is_ok_and(|exit_key| key == *exit_key)
+    }
 }
 
 pub struct Events {
     rx: mpsc::Receiver<Event<Key>>,
+    /// Blocks on stdin, left detached on drop
     input_handle: thread::JoinHandle<()>,
-    ignore_exit_key: Arc<AtomicBool>,
-    tick_handle: thread::JoinHandle<()>,
+    exit_key: ExitKey,
+    /// Asks the threads joined on drop to stop
+    shutdown: Arc<AtomicBool>,
+    tick_handle: Option<thread::JoinHandle<()>>,
+    /// Dropped to wake the tick thread up, which waits on it between ticks
+    tick_stop: Option<mpsc::Sender<()>>,
+    /// Blocks on the i3 socket, left detached on drop
     i3_handle: thread::JoinHandle<()>,
+    debounce_handle: Option<thread::JoinHandle<()>>,
+    resize_handle: Option<thread::JoinHandle<()>>,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug)]
 pub struct Config {
     pub exit_key: Key,
     pub tick_rate: Duration,
+    /// i3 events triggering an `Event::I3`
+    pub subscriptions: Vec<Subscription>,
+    /// i3 events arriving within this long of the first one make a single `Event::I3`
+    pub debounce: Duration,
 }
 
 impl Default for Config {
@@ -36,6 +91,13 @@ impl Default for Config {
         Config {
             exit_key: Key::Char('q'),
             tick_rate: Duration::from_millis(250),
+            subscriptions: vec![
+                Subscription::Window,
+                Subscription::Workspace,
+                Subscription::Output,
+                Subscription::Mode,
+            ],
+            debounce: Duration::from_millis(50),
         }
     }
 }
@@ -47,53 +109,121 @@ impl Events {
 
     pub fn with_config(config: Config, mut i3_listener: I3EventListener) -> Events {
         let (tx, rx) = mpsc::channel();
-        let ignore_exit_key = Arc::new(AtomicBool::new(false));
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let Config {
+            exit_key,
+            tick_rate,
+            subscriptions,
+            debounce,
+        } = config;
+        let exit_key = ExitKey::new(exit_key);
         let input_handle = {
             let tx = tx.clone();
-            let ignore_exit_key = ignore_exit_key.clone();
+            let exit_key = exit_key.clone();
             thread::spawn(move || {
                 let stdin = io::stdin();
-                for evt in stdin.keys() {
-                    if let Ok(key) = evt {
-                        if let Err(err) = tx.send(Event::Input(key)) {
-                            eprintln!("{}", err);
-                            return;
-                        }
-                        if !ignore_exit_key.load(Ordering::Relaxed) && key == config.exit_key {
-                            return;
-                        }
+                for event in stdin.events().flatten() {
+                    let (event, exit) = match event {
+                        TermEvent::Key(key) => (Event::Input(key), exit_key.matches(key)),
+                        TermEvent::Mouse(mouse) => (Event::Mouse(mouse), false),
+                        TermEvent::Unsupported(_) => continue,
+                    };
+                    if let Err(err) = tx.send(event) {
+                        eprintln!("{}", err);
+                        return;
+                    }
+                    if exit {
+                        return;
                     }
                 }
             })
         };
+        let (tick_stop, tick_stopped) = mpsc::channel::<()>();
         let tick_handle = {
             let tx = tx.clone();
+            // Whatever the tick rate, dropping the sender ends the wait
             thread::spawn(move || loop {
                 if tx.send(Event::Tick).is_err() {
                     break;
                 }
-                thread::sleep(config.tick_rate);
+                if tick_stopped.recv_timeout(tick_rate) != Err(mpsc::RecvTimeoutError::Timeout) {
+                    break;
+                }
             })
         };
+        let resize_handle = {
+            let tx = tx.clone();
+            let shutdown = shutdown.clone();
+            thread::spawn(move || {
+                let mut size = terminal_size().ok();
+                while !shutdown.load(Ordering::Relaxed) {
+                    thread::sleep(RESIZE_POLL_RATE);
+                    let new_size = terminal_size().ok();
+                    if new_size == size {
+                        continue;
+                    }
+                    size = new_size;
+                    if let Some((width, height)) = size {
+                        if tx.send(Event::Resize(width, height)).is_err() {
+                            break;
+                        }
+                    }
+                }
+            })
+        };
+        let (i3_tx, i3_rx) = mpsc::channel();
         let i3_handle = {
-            i3_listener.subscribe(&[Subscription::Window]).unwrap();
+            let tx = tx.clone();
+            let mut listening = i3_listener.subscribe(&subscriptions).is_ok();
 
-            let tx = tx;
-            thread::spawn(move || {
-                let events = i3_listener.listen();
-                for _event in events {
-                    if let Err(err) = tx.send(Event::I3) {
-                        eprintln!("{}", err)
-                    };
+            thread::spawn(move || loop {
+                // Ends with an error once i3 goes away, nothing comes without a subscription
+                if listening {
+                    for event in i3_listener.listen() {
+                        let event = match event {
+                            Ok(event) => event,
+                            Err(err) => {
+                                log::warn!("i3 event stream closed: {}", err);
+                                break;
+                            }
+                        };
+                        log::debug!("i3 event {}", describe(&event));
+                        // Leaves the tree as it is, no need to wait for the burst to end
+                        let sent = match event {
+                            I3Event::ModeEvent(info) => tx.send(Event::Mode(info.change)).is_ok(),
+                            _ => i3_tx.send(()).is_ok(),
+                        };
+                        if !sent {
+                            return;
+                        }
+                    }
+                }
+                if tx.send(Event::I3Disconnected).is_err() {
+                    return;
+                }
+                i3_listener = reconnect(&subscriptions);
+                listening = true;
+                log::info!("listening to i3 events again");
+                // The tree changed while nothing was listening
+                if tx.send(Event::I3Reconnected).is_err() {
+                    return;
                 }
             })
         };
+        let debounce_handle = {
+            let shutdown = shutdown.clone();
+            thread::spawn(move || coalesce(i3_rx, tx, debounce, &shutdown))
+        };
         Events {
             rx,
-            ignore_exit_key,
+            exit_key,
+            shutdown,
             input_handle,
-            tick_handle,
+            tick_handle: Some(tick_handle),
+            tick_stop: Some(tick_stop),
             i3_handle,
+            debounce_handle: Some(debounce_handle),
+            resize_handle: Some(resize_handle),
         }
     }
 
@@ -101,11 +231,171 @@ impl Events {
         self.rx.recv()
     }
 
+    /// Next event if one comes before `deadline`, waiting as `next` without one
+    pub fn next_until(
+        &self,
+        deadline: Option<Instant>,
+    ) -> Result<Option<Event<Key>>, mpsc::RecvError> {
+        let deadline = match deadline {
+            Some(deadline) => deadline,
+            None => return self.rx.recv().map(Some),
+        };
+        match self
+            .rx
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        {
+            Ok(event) => Ok(Some(event)),
+            Err(mpsc::RecvTimeoutError::Timeout) => Ok(None),
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(mpsc::RecvError),
+        }
+    }
+
+    /// Whether `key` is the exit key while it is enabled, no input follows it
+    pub fn is_exit_key(&self, key: Key) -> bool {
+        self.exit_key.matches(key)
+    }
+
+    pub fn set_exit_key(&mut self, key: Key) {
+        if let Ok(mut exit_key) = self.exit_key.key.lock() {
+            *exit_key = key;
+        }
+    }
+
     pub fn disable_exit_key(&mut self) {
-        self.ignore_exit_key.store(true, Ordering::Relaxed);
+        self.exit_key.ignored.store(true, Ordering::Relaxed);
     }
 
     pub fn enable_exit_key(&mut self) {
-        self.ignore_exit_key.store(false, Ordering::Relaxed);
+        self.exit_key.ignored.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Stops the threads that can be joined, stdin and the i3 socket have no
+/// timeout so the input and i3 threads are left to end with the process
+impl Drop for Events {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        self.tick_stop.take();
+        let handles = vec![
+            self.tick_handle.take(),
+            self.debounce_handle.take(),
+            self.resize_handle.take(),
+        ];
+        for handle in handles.into_iter().flatten() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Pause after `delay` before the next attempt at reconnecting
+fn backoff(delay: Duration) -> Duration {
+    (delay * 2).min(MAX_RECONNECT_DELAY)
+}
+
+/// Listener subscribed to `subscriptions`, retried until i3 is back
+fn reconnect(subscriptions: &[Subscription]) -> I3EventListener {
+    let mut delay = RECONNECT_DELAY;
+    loop {
+        thread::sleep(delay);
+        if let Ok(mut listener) = I3EventListener::connect() {
+            if listener.subscribe(subscriptions).is_ok() {
+                return listener;
+            }
+        }
+        delay = backoff(delay);
+    }
+}
+
+/// Kind of change an i3 event reports, for the log
+fn describe(event: &I3Event) -> String {
+    match event {
+        I3Event::WorkspaceEvent(info) => format!("workspace {:?}", info.change),
+        I3Event::OutputEvent(info) => format!("output {:?}", info.change),
+        I3Event::ModeEvent(info) => format!("mode {}", info.change),
+        I3Event::WindowEvent(info) => {
+            format!("window {:?} con_id {}", info.change, info.container.id)
+        }
+        I3Event::BarConfigEvent(_) => "barconfig_update".to_string(),
+        I3Event::BindingEvent(info) => format!("binding {:?}", info.change),
+        I3Event::ShutdownEvent(info) => format!("shutdown {:?}", info.change),
+    }
+}
+
+/// Forward one `Event::I3` per burst of i3 events, at most one per `window`
+fn coalesce(
+    rx: mpsc::Receiver<()>,
+    tx: mpsc::Sender<Event<Key>>,
+    window: Duration,
+    shutdown: &AtomicBool,
+) {
+    loop {
+        // Wake up regularly to notice the shutdown
+        match rx.recv_timeout(window) {
+            Ok(()) => {}
+            Err(mpsc::RecvTimeoutError::Timeout) if !shutdown.load(Ordering::Relaxed) => continue,
+            Err(_) => return,
+        }
+        let deadline = Instant::now() + window;
+        while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+            // Timed out, or the i3 thread is gone
+            if rx.recv_timeout(left).is_err() {
+                break;
+            }
+        }
+        if let Err(err) = tx.send(Event::I3) {
+            eprintln!("{}", err);
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bursts_are_coalesced() {
+        let (i3_tx, i3_rx) = mpsc::channel();
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..20 {
+            i3_tx.send(()).unwrap();
+        }
+        drop(i3_tx);
+        coalesce(
+            i3_rx,
+            tx,
+            Duration::from_millis(50),
+            &AtomicBool::new(false),
+        );
+        assert_eq!(
+            rx.iter().filter(|event| matches!(event, Event::I3)).count(),
+            1
+        );
+    }
+
+    #[test]
+    fn exit_key_can_be_ignored() {
+        let exit_key = ExitKey::new(Key::Esc);
+        assert!(exit_key.matches(Key::Esc));
+        assert!(!exit_key.matches(Key::Char('q')));
+        exit_key.clone().ignored.store(true, Ordering::Relaxed);
+        assert!(!exit_key.matches(Key::Esc));
+        exit_key.ignored.store(false, Ordering::Relaxed);
+        *exit_key.key.lock().unwrap() = Key::Char('q');
+        assert!(exit_key.matches(Key::Char('q')));
+    }
+
+    #[test]
+    fn coalescing_stops_on_shutdown() {
+        let (_i3_tx, i3_rx) = mpsc::channel();
+        let (tx, _rx) = mpsc::channel();
+        coalesce(i3_rx, tx, Duration::from_millis(10), &AtomicBool::new(true));
+    }
+
+    #[test]
+    fn reconnect_delay_doubles_up_to_a_limit() {
+        assert_eq!(backoff(RECONNECT_DELAY), Duration::from_secs(1));
+        assert_eq!(backoff(Duration::from_secs(6)), MAX_RECONNECT_DELAY);
+        assert_eq!(backoff(MAX_RECONNECT_DELAY), MAX_RECONNECT_DELAY);
     }
 }
@@ -0,0 +1,255 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    error::Error,
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use i3ipc::reply::{Node, WindowProperty};
+use serde::Serialize;
+
+use crate::NodeId;
+
+/// Serializable mirror of the `i3ipc::reply::Node` fields shown in the tree
+#[derive(Serialize)]
+struct ExportNode {
+    con_id: NodeId,
+    name: Option<String>,
+    node_type: String,
+    layout: String,
+    focused: bool,
+    urgent: bool,
+    nodes: Vec<ExportNode>,
+    floating_nodes: Vec<ExportNode>,
+}
+
+impl From<&Node> for ExportNode {
+    fn from(node: &Node) -> Self {
+        Self {
+            con_id: node.id,
+            name: node.name.clone(),
+            node_type: format!("{:?}", node.nodetype),
+            layout: format!("{:?}", node.layout),
+            focused: node.focused,
+            urgent: node.urgent,
+            nodes: node.nodes.iter().map(ExportNode::from).collect(),
+            floating_nodes: node.floating_nodes.iter().map(ExportNode::from).collect(),
+        }
+    }
+}
+
+pub(crate) fn tree_to_json(node: &Node) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&ExportNode::from(node))
+}
+
+/// Serializable mirror of every field `i3ipc::reply::Node` reads from the
+/// reply, named as i3 does
+#[derive(Serialize)]
+struct RawNode {
+    id: NodeId,
+    name: Option<String>,
+    #[serde(rename = "type")]
+    node_type: String,
+    layout: String,
+    border: String,
+    current_border_width: i32,
+    percent: Option<f64>,
+    rect: (i32, i32, i32, i32),
+    window_rect: (i32, i32, i32, i32),
+    deco_rect: (i32, i32, i32, i32),
+    geometry: (i32, i32, i32, i32),
+    window: Option<i32>,
+    window_properties: Option<BTreeMap<&'static str, String>>,
+    urgent: bool,
+    focused: bool,
+    focus: Vec<NodeId>,
+    nodes: Vec<RawNode>,
+    floating_nodes: Vec<RawNode>,
+}
+
+fn property_name(property: &WindowProperty) -> &'static str {
+    match property {
+        WindowProperty::Title => "title",
+        WindowProperty::Instance => "instance",
+        WindowProperty::Class => "class",
+        WindowProperty::WindowRole => "window_role",
+        WindowProperty::TransientFor => "transient_for",
+    }
+}
+
+impl From<&Node> for RawNode {
+    fn from(node: &Node) -> Self {
+        Self {
+            id: node.id,
+            name: node.name.clone(),
+            node_type: format!("{:?}", node.nodetype),
+            layout: format!("{:?}", node.layout),
+            border: format!("{:?}", node.border),
+            current_border_width: node.current_border_width,
+            percent: node.percent,
+            rect: node.rect,
+            window_rect: node.window_rect,
+            deco_rect: node.deco_rect,
+            geometry: node.geometry,
+            window: node.window,
+            window_properties: node.window_properties.as_ref().map(|properties| {
+                properties
+                    .iter()
+                    .map(|(property, value)| (property_name(property), value.clone()))
+                    .collect()
+            }),
+            urgent: node.urgent,
+            focused: node.focused,
+            focus: node.focus.clone(),
+            nodes: node.nodes.iter().map(RawNode::from).collect(),
+            floating_nodes: node.floating_nodes.iter().map(RawNode::from).collect(),
+        }
+    }
+}
+
+/// Everything known of `node` and its subtree, as pretty JSON
+pub(crate) fn node_to_json(node: &Node) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&RawNode::from(node))
+}
+
+/// Quote `label` for DOT, escaping what would end the string early and
+/// turning line breaks into DOT ones
+fn dot_string(label: &str) -> String {
+    let escaped = label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
+
+fn write_dot_nodes(node: &Node, dot: &mut String) {
+    let label = format!(
+        "{}\n[{:?}] {{{:?}}}",
+        node.name.as_deref().unwrap_or_default(),
+        node.nodetype,
+        node.layout
+    );
+    dot.push_str(&format!(
+        "    {} [label={}];\n",
+        node.id,
+        dot_string(&label)
+    ));
+    for child in node.nodes.iter().chain(&node.floating_nodes) {
+        dot.push_str(&format!("    {} -> {};\n", node.id, child.id));
+        write_dot_nodes(child, dot);
+    }
+}
+
+/// Graphviz digraph with one vertex per container, identified by its con_id
+pub(crate) fn tree_to_dot(node: &Node) -> String {
+    let mut dot = String::from("digraph i3_tree {\n");
+    write_dot_nodes(node, &mut dot);
+    dot.push_str("}\n");
+    dot
+}
+
+/// `/tmp/i3-tree-<timestamp in milliseconds>-<pid>.<extension>`, so that
+/// exports in a row or from two instances don't share a file
+fn export_path(extension: &str) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or_default();
+    env::temp_dir().join(format!(
+        "i3-tree-{}-{}.{}",
+        timestamp,
+        process::id(),
+        extension
+    ))
+}
+
+/// Write `contents` to a new file at `path`. An existing file, or a symlink
+/// planted in the shared temp directory, is an error rather than overwritten
+fn write_new(path: &Path, contents: &str) -> Result<(), Box<dyn Error>> {
+    let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+    file.write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+/// Write the tree as pretty JSON, returning the path of the file
+pub(crate) fn export_json(node: &Node) -> Result<PathBuf, Box<dyn Error>> {
+    let path = export_path("json");
+    write_new(&path, &tree_to_json(node)?)?;
+    Ok(path)
+}
+
+/// Write the tree as a Graphviz digraph, returning the path of the file
+pub(crate) fn export_dot(node: &Node) -> Result<PathBuf, Box<dyn Error>> {
+    let path = export_path("dot");
+    write_new(&path, &tree_to_dot(node))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::node;
+
+    #[test]
+    fn json_lists_children() {
+        let json = tree_to_json(&node(1, vec![node(2, vec![]), node(3, vec![])])).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["con_id"], 1);
+        assert_eq!(value["nodes"][1]["name"], "node 3");
+        assert_eq!(value["floating_nodes"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn raw_json_keeps_every_field() {
+        let mut window = node(2, vec![]);
+        window.window_properties = Some(
+            vec![(WindowProperty::Class, "Firefox".to_string())]
+                .into_iter()
+                .collect(),
+        );
+        let json = node_to_json(&node(1, vec![window])).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["type"], "Con");
+        assert_eq!(value["border"], "Normal");
+        assert_eq!(value["nodes"][0]["window_properties"]["class"], "Firefox");
+    }
+
+    #[test]
+    fn dot_links_parents_to_children() {
+        let dot = tree_to_dot(&node(1, vec![node(2, vec![node(3, vec![])])]));
+        assert!(dot.starts_with("digraph i3_tree {\n"));
+        assert!(dot.contains("    1 -> 2;\n"));
+        assert!(dot.contains("    2 -> 3;\n"));
+        assert!(dot.contains("    3 [label=\"node 3\\n[Con] {SplitH}\"];\n"));
+    }
+
+    #[test]
+    fn dot_escapes_names() {
+        let mut tricky = node(1, vec![]);
+        tricky.name = Some(r#"say "hi" \o/"#.to_string());
+        let dot = tree_to_dot(&tricky);
+        assert!(dot.contains(r#"[label="say \"hi\" \\o/\n"#));
+    }
+
+    #[test]
+    fn json_handles_deep_trees() {
+        let deep = (0..500).fold(node(500, vec![]), |child, id| node(id, vec![child]));
+        assert!(tree_to_json(&deep).unwrap().contains("\"node 500\""));
+    }
+
+    #[test]
+    fn exports_never_overwrite_a_file() {
+        let path = export_path("test");
+        assert!(path
+            .to_string_lossy()
+            .ends_with(&format!("-{}.test", process::id())));
+        write_new(&path, "first").unwrap();
+        assert!(write_new(&path, "second").is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "first");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
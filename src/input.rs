@@ -0,0 +1,126 @@
+use tui::{
+    style::{Modifier, Style},
+    text::{Span, Spans},
+    widgets::Paragraph,
+};
+
+/// Line of text being typed, edited where the cursor is
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct InputField {
+    text: String,
+    /// Characters left of the cursor
+    cursor: usize,
+}
+
+impl InputField {
+    pub(crate) fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Byte offset of the `index`th character, the end past the last one
+    fn offset(&self, index: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(index)
+            .map_or(self.text.len(), |(offset, _)| offset)
+    }
+
+    pub(crate) fn insert_char(&mut self, c: char) {
+        let offset = self.offset(self.cursor);
+        self.text.insert(offset, c);
+        self.cursor += 1;
+    }
+
+    /// Erase the character left of the cursor
+    pub(crate) fn backspace(&mut self) {
+        if self.cursor == 0 {
+            return;
+        }
+        self.cursor -= 1;
+        let offset = self.offset(self.cursor);
+        self.text.remove(offset);
+    }
+
+    pub(crate) fn cursor_left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub(crate) fn cursor_right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.text.chars().count());
+    }
+
+    /// Text typed so far, the field is left empty
+    pub(crate) fn take(&mut self) -> String {
+        self.cursor = 0;
+        std::mem::take(&mut self.text)
+    }
+
+    /// `prefix` then the text, the character under the cursor reversed, a
+    /// block past the end
+    pub(crate) fn spans(&self, prefix: &str) -> Spans<'static> {
+        let (before, after) = self.text.split_at(self.offset(self.cursor));
+        let mut after = after.chars();
+        let under = after.next().map_or("█".to_string(), String::from);
+        Spans::from(vec![
+            Span::raw(format!("{}{}", prefix, before)),
+            Span::styled(under, Style::default().add_modifier(Modifier::REVERSED)),
+            Span::raw(after.as_str().to_string()),
+        ])
+    }
+
+    pub(crate) fn render(&self, prefix: &str) -> Paragraph<'static> {
+        Paragraph::new(self.spans(prefix))
+    }
+}
+
+impl From<&str> for InputField {
+    fn from(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            cursor: text.chars().count(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_happen_at_the_cursor() {
+        let mut input = InputField::from("mové");
+        input.cursor_left();
+        input.cursor_left();
+        input.insert_char('x');
+        assert_eq!(input.text(), "moxvé");
+        input.cursor_right();
+        input.cursor_right();
+        input.cursor_right();
+        input.backspace();
+        assert_eq!(input.text(), "moxv");
+        input.cursor_left();
+        input.cursor_left();
+        input.cursor_left();
+        input.cursor_left();
+        input.cursor_left();
+        input.backspace();
+        assert_eq!(input.text(), "moxv");
+        assert_eq!(input.take(), "moxv");
+        assert_eq!(input.text(), "");
+    }
+
+    #[test]
+    fn the_cursor_is_drawn_reversed() {
+        let mut input = InputField::from("ab");
+        let text = |spans: Spans| -> Vec<String> {
+            spans
+                .0
+                .into_iter()
+                .map(|span| span.content.into_owned())
+                .collect()
+        };
+        assert_eq!(text(input.spans(":")), vec![":ab", "█", ""]);
+        input.cursor_left();
+        assert_eq!(text(input.spans(":")), vec![":a", "b", ""]);
+    }
+}
@@ -0,0 +1,243 @@
+use std::{
+    collections::HashMap,
+    env,
+    io::{self, Read, Write},
+    os::unix::net::UnixStream,
+    process::Command,
+};
+
+use i3ipc::reply::{Node, NodeBorder, NodeLayout, NodeType, WindowProperty};
+use serde_json::Value;
+
+use crate::NodeId;
+
+static MAGIC: &[u8] = b"i3-ipc";
+const GET_TREE: u32 = 4;
+
+/// Tree properties that `i3ipc::reply::Node` doesn't expose
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct NodeExtra {
+    pub(crate) fullscreen_mode: u8,
+    pub(crate) marks: Vec<String>,
+    /// Wayland application id, Sway only
+    pub(crate) app_id: Option<String>,
+    /// Inner and outer gaps of workspaces, when i3 handles gaps
+    pub(crate) gaps: Option<(i64, i64)>,
+    /// Process owning the window, Sway only
+    pub(crate) pid: Option<u32>,
+}
+
+pub(crate) type NodeExtras = HashMap<NodeId, NodeExtra>;
+
+/// Every mark set on a container, sorted
+pub(crate) fn marks(extras: &NodeExtras) -> Vec<String> {
+    let mut marks: Vec<String> = extras
+        .values()
+        .flat_map(|extra| extra.marks.iter().cloned())
+        .collect();
+    marks.sort();
+    marks
+}
+
+/// Bare IPC socket used to read the raw JSON replies of i3
+pub(crate) struct RawConnection(UnixStream);
+
+impl RawConnection {
+    pub(crate) fn connect() -> io::Result<Self> {
+        Ok(Self(UnixStream::connect(socket_path()?)?))
+    }
+
+    /// The tree and its extras, both read from the same reply so that they
+    /// always agree
+    pub(crate) fn get_tree(&mut self) -> io::Result<(Node, NodeExtras)> {
+        let tree = self.send_receive(GET_TREE, "")?;
+        let mut extras = NodeExtras::new();
+        let node = build_node(&tree, &mut extras);
+        Ok((node, extras))
+    }
+
+    fn send_receive(&mut self, message_type: u32, payload: &str) -> io::Result<Value> {
+        let mut message = MAGIC.to_vec();
+        message.extend(&(payload.len() as u32).to_le_bytes());
+        message.extend(&message_type.to_le_bytes());
+        message.extend(payload.bytes());
+        self.0.write_all(&message)?;
+
+        let mut header = [0_u8; 14];
+        self.0.read_exact(&mut header)?;
+        if &header[..6] != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "bad i3-ipc magic",
+            ));
+        }
+        let mut length = [0_u8; 4];
+        length.copy_from_slice(&header[6..10]);
+        let mut reply = vec![0_u8; u32::from_le_bytes(length) as usize];
+        self.0.read_exact(&mut reply)?;
+        serde_json::from_slice(&reply).map_err(io::Error::from)
+    }
+}
+
+/// Same lookup as `i3ipc`, so both connections reach the same WM
+fn socket_path() -> io::Result<String> {
+    if let Ok(path) = env::var("I3SOCK").or_else(|_| env::var("SWAYSOCK")) {
+        return Ok(path);
+    }
+    let output = Command::new("i3").arg("--get-socketpath").output()?;
+    if !output.status.success() {
+        return Err(io::Error::other("i3 --get-socketpath didn't return 0"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string())
+}
+
+fn build_rect(rect: &Value) -> (i32, i32, i32, i32) {
+    let field = |name: &str| rect[name].as_i64().unwrap_or_default() as i32;
+    (field("x"), field("y"), field("width"), field("height"))
+}
+
+fn build_window_properties(properties: &Value) -> Option<HashMap<WindowProperty, String>> {
+    let properties = properties.as_object()?;
+    let known = [
+        ("title", WindowProperty::Title),
+        ("instance", WindowProperty::Instance),
+        ("class", WindowProperty::Class),
+        ("window_role", WindowProperty::WindowRole),
+        ("transient_for", WindowProperty::TransientFor),
+    ];
+    Some(
+        known
+            .iter()
+            .filter_map(|(name, property)| match &properties.get(*name)? {
+                Value::String(value) => Some((property.clone(), value.clone())),
+                Value::Number(value) => Some((property.clone(), value.to_string())),
+                _ => None,
+            })
+            .collect(),
+    )
+}
+
+/// `Node` of a GET_TREE reply as `i3ipc` reads it, collecting what it leaves
+/// out into `extras` on the way. Missing fields are left empty rather than
+/// failing, Sway omits some of them
+fn build_node(node: &Value, extras: &mut NodeExtras) -> Node {
+    let id = node["id"].as_i64().unwrap_or_default();
+    extras.insert(id, build_extra(node));
+    let children = |name: &str, extras: &mut NodeExtras| -> Vec<Node> {
+        node[name]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|child| build_node(child, extras))
+            .collect()
+    };
+    let nodes = children("nodes", extras);
+    let floating_nodes = children("floating_nodes", extras);
+    Node {
+        focus: node["focus"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_i64)
+            .collect(),
+        nodes,
+        floating_nodes,
+        id,
+        name: node["name"].as_str().map(str::to_string),
+        nodetype: match node["type"].as_str().unwrap_or_default() {
+            "root" => NodeType::Root,
+            "output" => NodeType::Output,
+            "con" => NodeType::Con,
+            "floating_con" => NodeType::FloatingCon,
+            "workspace" => NodeType::Workspace,
+            "dockarea" => NodeType::DockArea,
+            _ => NodeType::Unknown,
+        },
+        border: match node["border"].as_str().unwrap_or_default() {
+            "normal" => NodeBorder::Normal,
+            "none" => NodeBorder::None,
+            "pixel" => NodeBorder::Pixel,
+            _ => NodeBorder::Unknown,
+        },
+        current_border_width: node["current_border_width"].as_i64().unwrap_or_default() as i32,
+        layout: match node["layout"].as_str().unwrap_or_default() {
+            "splith" => NodeLayout::SplitH,
+            "splitv" => NodeLayout::SplitV,
+            "stacked" => NodeLayout::Stacked,
+            "tabbed" => NodeLayout::Tabbed,
+            "dockarea" => NodeLayout::DockArea,
+            "output" => NodeLayout::Output,
+            _ => NodeLayout::Unknown,
+        },
+        percent: node["percent"].as_f64(),
+        rect: build_rect(&node["rect"]),
+        window_rect: build_rect(&node["window_rect"]),
+        deco_rect: build_rect(&node["deco_rect"]),
+        geometry: build_rect(&node["geometry"]),
+        window: node["window"].as_i64().map(|window| window as i32),
+        window_properties: build_window_properties(&node["window_properties"]),
+        urgent: node["urgent"].as_bool().unwrap_or_default(),
+        focused: node["focused"].as_bool().unwrap_or_default(),
+    }
+}
+
+fn build_extra(node: &Value) -> NodeExtra {
+    NodeExtra {
+        fullscreen_mode: node["fullscreen_mode"].as_u64().unwrap_or_default() as u8,
+        marks: node["marks"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|mark| mark.as_str().map(str::to_string))
+            .collect(),
+        app_id: node["app_id"].as_str().map(str::to_string),
+        gaps: node["gaps"]["inner"]
+            .as_i64()
+            .zip(node["gaps"]["outer"].as_i64()),
+        pid: node["pid"].as_u64().map(|pid| pid as u32),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tree_and_extras_come_from_one_reply() {
+        let reply: Value = serde_json::from_str(
+            r#"{
+                "id": 1, "type": "root", "name": "root", "layout": "splith",
+                "border": "normal", "nodes": [{
+                    "id": 2, "type": "con", "name": "term", "layout": "splith",
+                    "border": "pixel", "current_border_width": 1, "window": 42,
+                    "window_properties": {"class": "URxvt", "transient_for": null},
+                    "rect": {"x": 0, "y": 0, "width": 800, "height": 600},
+                    "percent": 0.5, "focused": true, "marks": ["a"], "fullscreen_mode": 1
+                }],
+                "floating_nodes": [], "focus": [2]
+            }"#,
+        )
+        .unwrap();
+        let mut extras = NodeExtras::new();
+        let root = build_node(&reply, &mut extras);
+        assert_eq!(root.nodetype, NodeType::Root);
+        assert_eq!(root.focus, vec![2]);
+        let term = &root.nodes[0];
+        assert_eq!(term.window, Some(42));
+        assert_eq!(term.border, NodeBorder::Pixel);
+        assert_eq!(term.rect, (0, 0, 800, 600));
+        assert_eq!(term.percent, Some(0.5));
+        assert!(term.focused);
+        let properties = term.window_properties.as_ref().unwrap();
+        assert_eq!(
+            properties.get(&WindowProperty::Class).map(String::as_str),
+            Some("URxvt")
+        );
+        assert!(!properties.contains_key(&WindowProperty::TransientFor));
+        assert_eq!(extras[&2].marks, vec!["a"]);
+        assert_eq!(extras[&2].fullscreen_mode, 1);
+        assert_eq!(extras.len(), 2);
+    }
+}
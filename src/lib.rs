@@ -0,0 +1,2831 @@
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    env,
+    error::Error,
+    io,
+    time::{Duration, Instant},
+};
+
+use i3ipc::{
+    reply::{Node, NodeBorder, NodeLayout, NodeType, Workspace},
+    I3EventListener,
+};
+use termion::event::{Key, MouseButton, MouseEvent};
+
+use crate::backend::TreeBackend;
+use crate::config::{Action, Config, Keymap, Options, Theme};
+use crate::event::{Event, Events};
+use crate::input::InputField;
+use crate::ipc::NodeExtras;
+
+mod backend;
+#[doc(hidden)]
+pub mod bench;
+mod cli;
+mod clipboard;
+mod config;
+#[allow(dead_code)]
+mod event;
+mod export;
+mod input;
+mod ipc;
+mod logger;
+mod persist;
+mod pid;
+mod ui;
+
+type NodeId = i64;
+
+/// Pixels, or percentage points for tiled containers, of each resize
+const RESIZE_STEP: u32 = 5;
+
+/// Pixels added to or taken from the gaps at each step
+const GAPS_STEP: u32 = 5;
+
+/// Pixels a floating window moves at each step
+const FLOATING_STEP: u32 = 20;
+
+/// Rows the selection moves for each notch of the mouse wheel
+const WHEEL_STEP: usize = 3;
+
+/// Longest time between the two clicks of a double click
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// Time the selection must stay put before live focus focuses it
+const LIVE_FOCUS_DELAY: Duration = Duration::from_millis(150);
+
+/// Moves and swaps that can be undone
+const UNDO_DEPTH: usize = 20;
+
+/// Command held back until it is confirmed
+enum Pending {
+    /// Kill the container with this con_id
+    Kill(NodeId),
+    /// Hide the container with this con_id in the scratchpad
+    MoveToScratchpad(NodeId),
+    /// Typed command that closes windows or ends the session
+    Command(String),
+}
+
+/// Keys labels are typed with, home row first
+static LABEL_KEYS: &str = "asdfghjklqwertyuiopzxcvbnm";
+
+/// One letter labels when there are few enough rows, two letter ones
+/// otherwise so that no label is the prefix of another
+fn jump_labels(count: usize) -> Vec<String> {
+    let keys: Vec<char> = LABEL_KEYS.chars().collect();
+    if count <= keys.len() {
+        return keys.iter().take(count).map(char::to_string).collect();
+    }
+    keys.iter()
+        .flat_map(|first| {
+            keys.iter()
+                .map(move |second| format!("{}{}", first, second))
+        })
+        .take(count)
+        .collect()
+}
+
+/// Direction a container moves back in. Moving back only restores the
+/// position when the first move didn't enter or leave a container
+fn opposite(direction: &str) -> Option<&'static str> {
+    match direction {
+        "left" => Some("right"),
+        "right" => Some("left"),
+        "up" => Some("down"),
+        "down" => Some("up"),
+        _ => None,
+    }
+}
+
+/// Whether one of the `;` or `,` separated commands kills, exits or hides containers
+fn is_destructive(command: &str) -> bool {
+    command.split([';', ',']).any(|command| {
+        // Criteria come first and may contain anything
+        let command = command
+            .trim_start()
+            .strip_prefix('[')
+            .and_then(|rest| rest.split_once(']'))
+            .map_or(command, |(_, command)| command);
+        let words: Vec<&str> = command.split_whitespace().collect();
+        match words.first() {
+            Some(&"kill") | Some(&"exit") => true,
+            Some(&"move") => words.contains(&"scratchpad"),
+            _ => false,
+        }
+    })
+}
+
+enum StateMode {
+    Move(NodeId),
+    Resize(NodeId),
+    /// Changes the gaps of the focused workspace
+    Gaps,
+    /// Changes how the container holding the selection lays out its children
+    Layout,
+    /// Container to swap with the one selected next
+    Swap(NodeId),
+    /// Waits for `y` before running the pending command
+    Confirm(Pending),
+    /// Query being typed
+    Search(InputField),
+    /// i3 command being typed
+    Command(InputField),
+    /// Marks being typed for the selection
+    Mark(InputField),
+    /// Container to move and the workspace number being typed
+    MoveToWorkspace(NodeId, InputField),
+    /// Label of each row shown when the mode started, and what was typed of one
+    Jump(HashMap<String, NodeId>, InputField),
+    /// Row of the workspace pane about to be switched to
+    Workspaces(usize),
+    /// Row of the mark pane whose container is about to be selected
+    Marks(usize),
+    /// Lines scrolled past at the top of the JSON pane
+    RawJson(u16),
+    Help,
+    None,
+}
+
+struct State {
+    node_tree: Node,
+    selected: NodeId,
+    node_ids: Vec<NodeId>,
+    /// Position of each of `node_ids`, rebuilt along with it
+    node_index: HashMap<NodeId, usize>,
+    mode: StateMode,
+    message_port: Box<dyn TreeBackend>,
+    node_extras: NodeExtras,
+    /// Every workspace, fetched along with the tree
+    workspaces: Vec<Workspace>,
+    /// Every mark, fetched along with the tree
+    marks: Vec<String>,
+    /// Bumped each time the tree is fetched again or folded
+    tree_generation: u64,
+    /// Containers whose children are hidden
+    collapsed: HashSet<NodeId>,
+    /// Output whose tree alone is shown
+    output_filter: Option<String>,
+    /// Show the focused workspace alone, whatever the output filter
+    workspace_only: bool,
+    /// Show workspaces and windows only, without the containers in between
+    compact: bool,
+    /// The view as `compact` shows it, rebuilt along with the rows
+    compact_tree: Option<Node>,
+    /// Last committed search query
+    search: Option<String>,
+    /// Rows matching `search` in tree order, kept up to date with the tree
+    matches: Vec<NodeId>,
+    keymap: Keymap,
+    options: Options,
+    theme: Theme,
+    /// Message shown at the bottom, such as the last command error
+    status: Option<String>,
+    /// When `status` was set, `None` for sticky messages that stay until
+    /// replaced
+    status_set_at: Option<Instant>,
+    /// The connection broke and is retried on each tick
+    disconnected: bool,
+    /// Commands are shown and logged, i3 never gets them
+    dry_run: bool,
+    /// Highlight with text colors only, for light terminals
+    no_background: bool,
+    /// Select the container i3 focuses each time the tree changes
+    follow_focus: bool,
+    /// Focus each container stepped to, sending the keys back to `own_window`
+    live_focus: bool,
+    /// When the selection of the last step gets focused
+    live_focus_at: Option<Instant>,
+    /// Window focused at startup, the terminal running this
+    own_window: Option<NodeId>,
+    /// X11 window and process of `own_window`, which find it again once a
+    /// restarted i3 gave it another con_id
+    own_window_ids: (Option<i32>, Option<u32>),
+    /// Row clicked last and when, to spot double clicks
+    last_click: Option<(NodeId, Instant)>,
+    /// Process of each X11 window asked about, `xprop` runs once per window.
+    /// Windows gone from the tree are dropped, their ids get reused
+    x11_pids: HashMap<i32, Option<u32>>,
+    /// Show the details pane next to the tree
+    show_details: bool,
+    /// Label windows by class rather than by title
+    show_class: bool,
+    /// Follow containers with their children count and depth
+    show_counts: bool,
+    /// Show the time and `i3_event_count` right of the status
+    show_clock: bool,
+    /// Bursts of i3 events received since startup
+    i3_event_count: u64,
+    /// i3 binding mode, as named in its config
+    binding_mode: String,
+    /// Digits typed before a motion, repeating it
+    count: Option<usize>,
+    /// Commands reverting the last moves and swaps, the latest last
+    undo: Vec<String>,
+}
+
+/// Ids of the rows of the tree, leaving out what `collapsed` nodes hold
+fn collect_ids(node: &Node, collapsed: &HashSet<NodeId>) -> Vec<i64> {
+    let mut ids = vec![node.id];
+    if !collapsed.contains(&node.id) {
+        ids.extend(
+            node.nodes
+                .iter()
+                .chain(&node.floating_nodes)
+                .flat_map(|child| collect_ids(child, collapsed)),
+        );
+    }
+    ids
+}
+
+/// Names of the outputs, children of the root
+fn output_names(tree: &Node) -> Vec<String> {
+    tree.nodes
+        .iter()
+        .filter(|node| node.nodetype == NodeType::Output)
+        .filter_map(|node| node.name.clone())
+        .collect()
+}
+
+/// Workspace holding the container i3 focuses
+fn focused_workspace(tree: &Node) -> Option<&Node> {
+    path_to(tree, focused_id(tree)?)?
+        .into_iter()
+        .rev()
+        .filter_map(|id| find_node(tree, id))
+        .find(|node| node.nodetype == NodeType::Workspace)
+}
+
+/// Subtree the rows are taken from: the focused workspace when `workspace_only`
+/// is set, or else the output named `output` when there is one
+fn view_root<'a>(tree: &'a Node, output: Option<&str>, workspace_only: bool) -> &'a Node {
+    let output = || {
+        tree.nodes
+            .iter()
+            .find(|node| node.nodetype == NodeType::Output && node.name.as_deref() == output)
+    };
+    Some(tree)
+        .filter(|_| workspace_only)
+        .and_then(focused_workspace)
+        .or_else(output)
+        .unwrap_or(tree)
+}
+
+/// Whether the compact view keeps `node`, split containers are left out
+fn compact_keeps(node: &Node) -> bool {
+    match node.nodetype {
+        NodeType::Root | NodeType::Output | NodeType::Workspace => true,
+        _ => node.window.is_some() || (node.nodes.is_empty() && node.floating_nodes.is_empty()),
+    }
+}
+
+/// `node` with the containers `compact_keeps` leaves out replaced by their
+/// children, floating ones among the floating nodes
+fn compact_tree(mut node: Node) -> Node {
+    let (mut nodes, mut floating_nodes) = (vec![], vec![]);
+    for child in std::mem::take(&mut node.nodes) {
+        splice(child, false, &mut nodes, &mut floating_nodes);
+    }
+    for child in std::mem::take(&mut node.floating_nodes) {
+        splice(child, true, &mut nodes, &mut floating_nodes);
+    }
+    node.nodes = nodes;
+    node.floating_nodes = floating_nodes;
+    node
+}
+
+fn splice(node: Node, floating: bool, nodes: &mut Vec<Node>, floating_nodes: &mut Vec<Node>) {
+    if compact_keeps(&node) {
+        let kept = compact_tree(node);
+        if floating {
+            floating_nodes.push(kept);
+        } else {
+            nodes.push(kept);
+        }
+        return;
+    }
+    for child in node.nodes {
+        splice(child, floating, nodes, floating_nodes);
+    }
+    for child in node.floating_nodes {
+        splice(child, true, nodes, floating_nodes);
+    }
+}
+
+/// Node with the given con_id in the subtree of `node`
+fn find_node(node: &Node, id: NodeId) -> Option<&Node> {
+    if node.id == id {
+        return Some(node);
+    }
+    node.nodes
+        .iter()
+        .chain(&node.floating_nodes)
+        .find_map(|n| find_node(n, id))
+}
+
+/// Container holding the one with the given con_id in the subtree of `node`
+fn find_parent(node: &Node, id: NodeId) -> Option<&Node> {
+    let mut children = node.nodes.iter().chain(&node.floating_nodes);
+    if children.any(|child| child.id == id) {
+        return Some(node);
+    }
+    node.nodes
+        .iter()
+        .chain(&node.floating_nodes)
+        .find_map(|n| find_parent(n, id))
+}
+
+/// Chain of con_ids from `node` down to `id`, both included
+fn path_to(node: &Node, id: NodeId) -> Option<Vec<NodeId>> {
+    if node.id == id {
+        return Some(vec![node.id]);
+    }
+    node.nodes
+        .iter()
+        .chain(&node.floating_nodes)
+        .find_map(|n| path_to(n, id))
+        .map(|mut path| {
+            path.insert(0, node.id);
+            path
+        })
+}
+
+/// Container of the subtree of `node` holding the X11 `window`, or else the
+/// one of the process `pid`
+fn find_window(
+    node: &Node,
+    extras: &NodeExtras,
+    window: Option<i32>,
+    pid: Option<u32>,
+) -> Option<NodeId> {
+    let pid_of = |node: &Node| extras.get(&node.id).and_then(|extra| extra.pid);
+    if (window.is_some() && node.window == window) || (pid.is_some() && pid_of(node) == pid) {
+        return Some(node.id);
+    }
+    node.nodes
+        .iter()
+        .chain(&node.floating_nodes)
+        .find_map(|child| find_window(child, extras, window, pid))
+}
+
+/// X11 windows of the subtree of `node`
+fn window_ids(node: &Node, windows: &mut HashSet<i32>) {
+    windows.extend(node.window);
+    for child in node.nodes.iter().chain(&node.floating_nodes) {
+        window_ids(child, windows);
+    }
+}
+
+/// Container i3 currently focuses
+fn focused_id(node: &Node) -> Option<NodeId> {
+    if node.focused {
+        return Some(node.id);
+    }
+    node.nodes
+        .iter()
+        .chain(&node.floating_nodes)
+        .find_map(focused_id)
+}
+
+fn matches_query(node: &Node, query: &str) -> bool {
+    node.name
+        .as_ref()
+        .is_some_and(|name| name.to_lowercase().contains(&query.to_lowercase()))
+}
+
+fn index_ids(node_ids: &[NodeId]) -> HashMap<NodeId, usize> {
+    node_ids
+        .iter()
+        .enumerate()
+        .map(|(index, id)| (*id, index))
+        .collect()
+}
+
+/// Row after, or before, `index` out of `len`, clamped or wrapped around at
+/// the ends, the first row when `index` is unknown
+fn step(index: Option<usize>, len: usize, forward: bool, wrap: bool) -> Option<usize> {
+    let last = len.checked_sub(1)?;
+    Some(match (index, forward) {
+        (None, _) => 0,
+        (Some(index), true) if index >= last => {
+            if wrap {
+                0
+            } else {
+                last
+            }
+        }
+        (Some(index), true) => index + 1,
+        (Some(0), false) => {
+            if wrap {
+                last
+            } else {
+                0
+            }
+        }
+        (Some(index), false) => index - 1,
+    })
+}
+
+impl State {
+    fn new(config: Config) -> Result<Self, Box<dyn Error>> {
+        Self::with_backend(backend::connect()?, config)
+    }
+
+    fn with_backend(
+        mut message_port: Box<dyn TreeBackend>,
+        config: Config,
+    ) -> Result<Self, Box<dyn Error>> {
+        let (node, node_extras) = message_port.get_tree_with_extras()?;
+        let node_ids = collect_ids(&node, &HashSet::new());
+        let own_window = focused_id(&node);
+        let own_window_ids =
+            own_window
+                .and_then(|own| find_node(&node, own))
+                .map_or((None, None), |own| {
+                    let pid = node_extras.get(&own.id).and_then(|extra| extra.pid);
+                    (own.window, pid)
+                });
+        Ok(Self {
+            selected: node.id,
+            node_index: index_ids(&node_ids),
+            node_ids,
+            node_tree: node,
+            mode: StateMode::None,
+            marks: ipc::marks(&node_extras),
+            node_extras,
+            workspaces: message_port.get_workspaces()?,
+            tree_generation: 0,
+            collapsed: HashSet::new(),
+            output_filter: None,
+            workspace_only: false,
+            compact: false,
+            compact_tree: None,
+            message_port,
+            search: None,
+            matches: vec![],
+            keymap: config.keymap,
+            options: config.options,
+            theme: config.theme,
+            status: None,
+            status_set_at: None,
+            disconnected: false,
+            dry_run: false,
+            no_background: false,
+            follow_focus: false,
+            live_focus: false,
+            live_focus_at: None,
+            own_window,
+            own_window_ids,
+            last_click: None,
+            x11_pids: HashMap::new(),
+            show_details: false,
+            show_class: false,
+            show_counts: false,
+            show_clock: false,
+            i3_event_count: 0,
+            binding_mode: "default".to_string(),
+            count: None,
+            undo: vec![],
+        })
+    }
+
+    /// Fetch the tree again, a failure means i3 went away, e.g. on `i3 restart`
+    fn update_tree(&mut self) {
+        let start = Instant::now();
+        match self.fetch_tree() {
+            Ok(()) => log::debug!(
+                "tree {} fetched in {:?}, {} rows",
+                self.tree_generation,
+                start.elapsed(),
+                self.node_ids.len()
+            ),
+            Err(err) => {
+                log::warn!("tree refresh failed: {}", err);
+                self.disconnected = true;
+                self.set_sticky_status("reconnecting…".to_string());
+            }
+        }
+    }
+
+    fn fetch_tree(&mut self) -> Result<(), Box<dyn Error>> {
+        let (node, node_extras) = self.message_port.get_tree_with_extras()?;
+        self.workspaces = self.message_port.get_workspaces()?;
+        self.marks = ipc::marks(&node_extras);
+        let mut windows = HashSet::new();
+        window_ids(&node, &mut windows);
+        self.x11_pids.retain(|window, _| windows.contains(window));
+        let path = path_to(&self.node_tree, self.selected).unwrap_or_default();
+        self.node_tree = node;
+        self.node_extras = node_extras;
+        self.refresh_own_window();
+        self.rebuild_compact();
+        let root = self.view_root();
+        let node_ids = collect_ids(root, &self.collapsed);
+        let node_index = index_ids(&node_ids);
+        // Keep the selection, or fall back to its nearest surviving ancestor
+        self.selected = path
+            .into_iter()
+            .rev()
+            .find(|id| node_index.contains_key(id))
+            .unwrap_or(root.id);
+        self.node_ids = node_ids;
+        self.node_index = node_index;
+        self.tree_generation += 1;
+        self.update_matches();
+        if self.follow_focus {
+            if let Some(focused) = focused_id(&self.node_tree) {
+                self.reveal(focused);
+                self.select_visible(focused);
+            }
+        }
+        Ok(())
+    }
+
+    /// Subtree the rows are taken from
+    fn view_root(&self) -> &Node {
+        if let Some(compact) = &self.compact_tree {
+            return compact;
+        }
+        view_root(
+            &self.node_tree,
+            self.output_filter.as_deref(),
+            self.workspace_only,
+        )
+    }
+
+    /// Take the compact view from the tree, or drop it
+    fn rebuild_compact(&mut self) {
+        self.compact_tree = None;
+        if self.compact {
+            let root = self.view_root().clone();
+            self.compact_tree = Some(compact_tree(root));
+        }
+    }
+
+    fn toggle_compact(&mut self) {
+        self.compact = !self.compact;
+        self.refresh_ids();
+    }
+
+    /// Select `id` unless the view leaves it out
+    fn select_visible(&mut self, id: NodeId) {
+        if self.node_index.contains_key(&id) {
+            self.selected = id;
+        }
+    }
+
+    /// Rows changed with the folding or the view, the selection moves up out
+    /// of hidden subtrees, or to the first row once out of view
+    fn refresh_ids(&mut self) {
+        self.rebuild_compact();
+        self.node_ids = collect_ids(self.view_root(), &self.collapsed);
+        self.node_index = index_ids(&self.node_ids);
+        self.tree_generation += 1;
+        self.update_matches();
+        if let Some(visible) = path_to(&self.node_tree, self.selected)
+            .unwrap_or_default()
+            .into_iter()
+            .rev()
+            .find(|id| self.node_index.contains_key(id))
+            .or_else(|| self.node_ids.first().copied())
+        {
+            self.selected = visible;
+        }
+    }
+
+    fn toggle_workspace_only(&mut self) {
+        self.workspace_only = !self.workspace_only;
+        self.refresh_ids();
+    }
+
+    /// Show the next output alone, every output after the last one
+    fn cycle_output(&mut self) {
+        let outputs = output_names(&self.node_tree);
+        let next = match self.output_filter.as_ref() {
+            None => 0,
+            Some(current) => outputs
+                .iter()
+                .position(|output| output == current)
+                .map_or(outputs.len(), |position| position + 1),
+        };
+        self.output_filter = outputs.get(next).cloned();
+        self.refresh_ids();
+    }
+
+    /// Expand the ancestors of `id` so that its row is shown
+    fn reveal(&mut self, id: NodeId) {
+        let ancestors = path_to(&self.node_tree, id).unwrap_or_default();
+        let hidden = ancestors[..ancestors.len().saturating_sub(1)]
+            .iter()
+            .filter(|ancestor| self.collapsed.remove(ancestor))
+            .count();
+        if hidden > 0 {
+            self.refresh_ids();
+        }
+    }
+
+    /// Fold every workspace and everything in them, leaving outputs open
+    fn collapse_all(&mut self) {
+        fn collect_folds(node: &Node, in_workspace: bool, collapsed: &mut HashSet<NodeId>) {
+            let in_workspace = in_workspace || node.nodetype == NodeType::Workspace;
+            let children: Vec<&Node> = node.nodes.iter().chain(&node.floating_nodes).collect();
+            if in_workspace && !children.is_empty() {
+                collapsed.insert(node.id);
+            }
+            for child in children {
+                collect_folds(child, in_workspace, collapsed);
+            }
+        }
+        collect_folds(&self.node_tree, false, &mut self.collapsed);
+        self.refresh_ids();
+    }
+
+    fn expand_all(&mut self) {
+        self.collapsed.clear();
+        self.refresh_ids();
+    }
+
+    /// Hide or show the children of the selection
+    fn toggle_collapse(&mut self) {
+        let has_children = find_node(&self.node_tree, self.selected)
+            .is_some_and(|node| !node.nodes.is_empty() || !node.floating_nodes.is_empty());
+        if !self.collapsed.remove(&self.selected) && has_children {
+            self.collapsed.insert(self.selected);
+        }
+        self.refresh_ids();
+    }
+
+    /// Try to reach i3 again after `update_tree` lost it, true once it is back
+    fn reconnect(&mut self) -> bool {
+        if !self.disconnected {
+            return false;
+        }
+        match backend::connect() {
+            Ok(message_port) => {
+                log::info!("reconnected to i3");
+                self.message_port = message_port;
+                self.disconnected = false;
+                self.clear_status();
+                // A restarted i3 starts over in its default mode
+                self.binding_mode = "default".to_string();
+                self.update_tree();
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Row of the selection
+    fn selected_index(&self) -> Option<usize> {
+        self.node_index.get(&self.selected).copied()
+    }
+
+    /// Add `key` to the count when it is a digit no action is bound to,
+    /// `0` only extends a count
+    fn push_count(&mut self, key: Key) -> bool {
+        let digit = match key {
+            Key::Char(c) if self.keymap.action(key).is_none() => c.to_digit(10),
+            _ => None,
+        };
+        match (digit, self.count) {
+            (Some(0), None) | (None, _) => false,
+            (Some(digit), count) => {
+                let count = count.unwrap_or_default();
+                self.count = Some(count.saturating_mul(10).saturating_add(digit as usize));
+                true
+            }
+        }
+    }
+
+    /// Times the next action repeats, the count is used up
+    fn take_count(&mut self) -> usize {
+        self.count.take().unwrap_or(1)
+    }
+
+    fn select_step(&mut self, forward: bool) {
+        let len = self.node_ids.len();
+        let wrap = self.options.wrap_navigation;
+        if let Some(index) = step(self.selected_index(), len, forward, wrap) {
+            self.selected = self.node_ids[index]
+        }
+    }
+
+    fn select_next(&mut self) {
+        self.select_step(true);
+        self.schedule_live_focus();
+    }
+
+    fn select_previous(&mut self) {
+        self.select_step(false);
+        self.schedule_live_focus();
+    }
+
+    /// Select the row at `index`, focus it when clicked twice in a row
+    fn click(&mut self, index: usize, at: Instant) {
+        let id = match self.node_ids.get(index) {
+            Some(id) => *id,
+            None => return,
+        };
+        let double = self.last_click.is_some_and(|(clicked, when)| {
+            clicked == id && at.duration_since(when) <= DOUBLE_CLICK
+        });
+        self.selected = id;
+        if double {
+            self.last_click = None;
+            self.focus_selected();
+        } else {
+            self.last_click = Some((id, at));
+        }
+    }
+
+    fn wheel(&mut self, forward: bool) {
+        for _ in 0..WHEEL_STEP {
+            if forward {
+                self.select_next();
+            } else {
+                self.select_previous();
+            }
+        }
+    }
+
+    /// Focus the selection once steps stop coming, not at each of them
+    fn schedule_live_focus(&mut self) {
+        if self.live_focus {
+            self.live_focus_at = Some(Instant::now() + LIVE_FOCUS_DELAY);
+        }
+    }
+
+    fn toggle_live_focus(&mut self) {
+        self.live_focus = !self.live_focus;
+        self.live_focus_at = None;
+        // Following the focus would select our own window back
+        if self.live_focus {
+            self.follow_focus = false;
+        }
+    }
+
+    /// Keep `own_window` pointing at the terminal: by its window or process
+    /// when its con_id left the tree, and at nothing when they did too
+    fn refresh_own_window(&mut self) {
+        if let Some(own) = self.own_window {
+            if find_node(&self.node_tree, own).is_some() {
+                return;
+            }
+        }
+        let (window, pid) = self.own_window_ids;
+        self.own_window = find_window(&self.node_tree, &self.node_extras, window, pid);
+    }
+
+    /// Focus the selection for i3 to show it, then give the keyboard back to
+    /// our own window
+    fn live_focus(&mut self) {
+        self.live_focus_at = None;
+        let mut command = self.focus_command();
+        if let Some(own) = self.own_window.filter(|own| *own != self.selected) {
+            command.push_str(&format!("; [con_id=\"{}\"] focus", own));
+        }
+        let _ = self.issue(&command);
+    }
+
+    /// Every i3 command goes through here. The status line reports why
+    /// i3 didn't run it, failing or in a dry run, so callers with nothing
+    /// left to do then can drop the error
+    fn issue(&mut self, command: &str) -> Result<(), String> {
+        if self.dry_run {
+            log::info!("dry run, not sent: {}", command);
+            let shown = format!("dry run: {}", command);
+            self.set_status(shown.clone());
+            return Err(shown);
+        }
+        let failure = match self.message_port.run_command(command) {
+            Ok(outcomes) => outcomes
+                .into_iter()
+                .find(|outcome| !outcome.success)
+                .map(|outcome| {
+                    outcome
+                        .error
+                        .unwrap_or_else(|| format!("{} failed", command))
+                }),
+            Err(err) => Some(err.to_string()),
+        };
+        log::debug!(
+            "command {}: {}",
+            command,
+            failure.as_deref().unwrap_or("ok")
+        );
+        match &failure {
+            Some(failure) => self.set_status(failure.clone()),
+            None => self.clear_status(),
+        }
+        failure.map_or(Ok(()), Err)
+    }
+
+    /// Show `message` until the status timeout of the options runs out
+    fn set_status(&mut self, message: String) {
+        self.status = Some(message);
+        self.status_set_at = Some(Instant::now());
+    }
+
+    /// Show `message` until another one replaces it
+    fn set_sticky_status(&mut self, message: String) {
+        self.status = Some(message);
+        self.status_set_at = None;
+    }
+
+    fn clear_status(&mut self) {
+        self.status = None;
+        self.status_set_at = None;
+    }
+
+    /// Clear the status once it is older than the timeout, true if it was
+    fn expire_status(&mut self, now: Instant) -> bool {
+        let expired = match (self.status_set_at, self.options.status_timeout()) {
+            (Some(set_at), Some(timeout)) => now.saturating_duration_since(set_at) >= timeout,
+            _ => false,
+        };
+        if expired {
+            self.clear_status();
+        }
+        expired
+    }
+
+    /// Issue `command` for the container `id` only
+    fn issue_on(&mut self, id: NodeId, command: &str) -> Result<(), String> {
+        self.issue(&format!("[con_id=\"{}\"] {}", id, command))
+    }
+
+    fn select_first(&mut self) {
+        if let Some(selected) = self.node_ids.first() {
+            self.selected = *selected
+        }
+    }
+
+    fn select_last(&mut self) {
+        if let Some(selected) = self.node_ids.last() {
+            self.selected = *selected
+        }
+    }
+
+    /// Query being typed, or else the last committed one
+    fn search_query(&self) -> Option<&str> {
+        match self.mode {
+            StateMode::Search(ref query) => Some(query.text()),
+            _ => self.search.as_deref(),
+        }
+        .filter(|query| !query.is_empty())
+    }
+
+    /// Ids of nodes matching the search query, in tree order
+    fn search_matches(&self) -> Vec<NodeId> {
+        match self.search_query() {
+            Some(query) => self
+                .node_ids
+                .iter()
+                .copied()
+                .filter(|id| {
+                    find_node(&self.node_tree, *id).is_some_and(|n| matches_query(n, query))
+                })
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Search matches along with all their ancestors
+    fn search_relevant(&self) -> HashSet<NodeId> {
+        self.search_matches()
+            .into_iter()
+            .flat_map(|id| path_to(&self.node_tree, id).unwrap_or_default())
+            .collect()
+    }
+
+    /// Closed windows drop out of the matches, new ones matching come in
+    fn update_matches(&mut self) {
+        if let StateMode::Search(_) = self.mode {
+            return;
+        }
+        self.matches = self.search_matches();
+    }
+
+    fn search_mode(&mut self) {
+        self.matches.clear();
+        self.mode = StateMode::Search(InputField::default());
+    }
+
+    /// Text typed in the prompt of the current mode
+    fn prompt(&mut self) -> Option<&mut InputField> {
+        match self.mode {
+            StateMode::Search(ref mut input)
+            | StateMode::Command(ref mut input)
+            | StateMode::Mark(ref mut input)
+            | StateMode::MoveToWorkspace(_, ref mut input)
+            | StateMode::Jump(_, ref mut input) => Some(input),
+            _ => None,
+        }
+    }
+
+    fn prompt_push(&mut self, c: char) {
+        if let Some(input) = self.prompt() {
+            input.insert_char(c);
+        }
+    }
+
+    fn prompt_pop(&mut self) {
+        if let Some(input) = self.prompt() {
+            input.backspace();
+        }
+    }
+
+    fn prompt_left(&mut self) {
+        if let Some(input) = self.prompt() {
+            input.cursor_left();
+        }
+    }
+
+    fn prompt_right(&mut self) {
+        if let Some(input) = self.prompt() {
+            input.cursor_right();
+        }
+    }
+
+    fn commit_search(&mut self) {
+        if let StateMode::Search(ref mut query) = self.mode {
+            self.search = Some(query.take()).filter(|query| !query.is_empty());
+        }
+        self.mode = StateMode::None;
+        self.update_matches();
+        if let Some(first) = self.matches.first() {
+            self.selected = *first;
+        }
+    }
+
+    fn cancel_search(&mut self) {
+        self.search = None;
+        self.matches.clear();
+        self.mode = StateMode::None;
+    }
+
+    fn export_json(&mut self) {
+        self.set_status(match export::export_json(&self.node_tree) {
+            Ok(path) => format!("tree exported to {}", path.display()),
+            Err(err) => format!("export failed: {}", err),
+        });
+    }
+
+    fn export_dot(&mut self) {
+        self.set_status(match export::export_dot(&self.node_tree) {
+            Ok(path) => format!("tree exported to {}", path.display()),
+            Err(err) => format!("export failed: {}", err),
+        });
+    }
+
+    /// Read the config file again, keeping the connection and the selection
+    fn reload_config(&mut self) {
+        match Config::load() {
+            Ok(config) => {
+                self.apply_config(config);
+                self.set_status("config reloaded".to_string());
+            }
+            Err(err) => self.set_status(format!("config not reloaded: {}", err)),
+        }
+    }
+
+    fn apply_config(&mut self, config: Config) {
+        self.keymap = config.keymap;
+        self.options = config.options;
+        self.theme = config.theme;
+        // Rows are styled with the theme
+        self.tree_generation += 1;
+    }
+
+    fn copy_id(&mut self) {
+        self.set_status(match clipboard::copy(&self.selected.to_string()) {
+            Ok(()) => format!("copied con_id {}", self.selected),
+            Err(err) => format!("copy failed: {}", err),
+        });
+    }
+
+    /// Marks of the selection, replaced by the ones of the prompt
+    fn selected_marks(&self) -> &[String] {
+        self.node_extras
+            .get(&self.selected)
+            .map_or(&[], |extra| extra.marks.as_slice())
+    }
+
+    fn commit_mark(&mut self) {
+        if let StateMode::Mark(ref mut input) = self.mode {
+            let input = input.take();
+            self.mode = StateMode::None;
+            if input.trim().is_empty() {
+                return;
+            }
+            let _ = self.issue_on(self.selected, &format!("mark {}", input));
+        }
+    }
+
+    fn move_to_workspace_mode(&mut self) {
+        self.mode = StateMode::MoveToWorkspace(self.selected, InputField::default());
+    }
+
+    fn jump_mode(&mut self) {
+        let labels = jump_labels(self.node_ids.len())
+            .into_iter()
+            .zip(self.node_ids.iter().copied())
+            .collect();
+        self.mode = StateMode::Jump(labels, InputField::default());
+    }
+
+    /// Select the row once its whole label is typed, keys no label goes on with are ignored
+    fn jump_push(&mut self, c: char) {
+        if let StateMode::Jump(ref labels, ref mut typed) = self.mode {
+            typed.insert_char(c);
+            if let Some(node_id) = labels.get(typed.text()) {
+                self.selected = *node_id;
+                self.mode = StateMode::None;
+            } else if !labels.keys().any(|label| label.starts_with(typed.text())) {
+                typed.backspace();
+            }
+        }
+    }
+
+    /// Open the workspace pane on the focused workspace, or close it
+    fn workspaces_mode(&mut self) {
+        match self.mode {
+            StateMode::None => {
+                let focused = self
+                    .workspaces
+                    .iter()
+                    .position(|workspace| workspace.focused);
+                self.mode = StateMode::Workspaces(focused.unwrap_or_default());
+            }
+            _ => self.mode = StateMode::None,
+        }
+    }
+
+    fn workspaces_step(&mut self, forward: bool) {
+        let len = self.workspaces.len();
+        let wrap = self.options.wrap_navigation;
+        if let StateMode::Workspaces(ref mut index) = self.mode {
+            if let Some(next) = step(Some(*index), len, forward, wrap) {
+                *index = next;
+            }
+        }
+    }
+
+    fn switch_workspace(&mut self) {
+        if let StateMode::Workspaces(index) = self.mode {
+            self.mode = StateMode::None;
+            if let Some(name) = self.workspaces.get(index).map(|ws| ws.name.clone()) {
+                let command = format!("workspace \"{}\"", name.replace('"', "\\\""));
+                if self.issue(&command).is_ok() {
+                    self.refresh_after(&format!("switched to workspace {}", name));
+                }
+            }
+        }
+    }
+
+    /// Open the mark pane, or close it
+    fn marks_mode(&mut self) {
+        match self.mode {
+            StateMode::None => self.mode = StateMode::Marks(0),
+            _ => self.mode = StateMode::None,
+        }
+    }
+
+    fn marks_step(&mut self, forward: bool) {
+        let len = self.marks.len();
+        let wrap = self.options.wrap_navigation;
+        if let StateMode::Marks(ref mut index) = self.mode {
+            if let Some(next) = step(Some(*index), len, forward, wrap) {
+                *index = next;
+            }
+        }
+    }
+
+    /// Open the JSON pane, or close it
+    fn raw_json_mode(&mut self) {
+        match self.mode {
+            StateMode::None => self.mode = StateMode::RawJson(0),
+            _ => self.mode = StateMode::None,
+        }
+    }
+
+    /// The selection as `export::node_to_json` prints it
+    fn raw_json(&self) -> String {
+        find_node(&self.node_tree, self.selected)
+            .map(|node| {
+                export::node_to_json(node).unwrap_or_else(|err| format!("not serialized: {}", err))
+            })
+            .unwrap_or_default()
+    }
+
+    /// Scroll the JSON pane a page, keeping its last line in view
+    fn raw_json_page(&mut self, forward: bool) {
+        let last = self.raw_json().lines().count().saturating_sub(1);
+        let last = u16::try_from(last).unwrap_or(u16::MAX);
+        let page = ui::side_pane_height();
+        if let StateMode::RawJson(ref mut scroll) = self.mode {
+            *scroll = if forward {
+                scroll.saturating_add(page).min(last)
+            } else {
+                scroll.saturating_sub(page)
+            };
+        }
+    }
+
+    /// Select another row, the JSON pane shows it from the top
+    fn raw_json_step(&mut self, forward: bool) {
+        self.select_step(forward);
+        if let StateMode::RawJson(ref mut scroll) = self.mode {
+            *scroll = 0;
+        }
+    }
+
+    /// Container holding `mark`, marks are unique in i3
+    fn marked_id(&self, mark: &str) -> Option<NodeId> {
+        self.node_extras
+            .iter()
+            .find(|(_, extra)| extra.marks.iter().any(|marked| marked == mark))
+            .map(|(id, _)| *id)
+    }
+
+    /// Select the container holding the chosen mark
+    fn select_mark(&mut self) {
+        if let StateMode::Marks(index) = self.mode {
+            self.mode = StateMode::None;
+            let mark = match self.marks.get(index) {
+                Some(mark) => mark.clone(),
+                None => return,
+            };
+            match self.marked_id(&mark) {
+                Some(id) => {
+                    self.reveal(id);
+                    self.select_visible(id);
+                    if self.selected != id {
+                        self.set_status(format!("{} is out of view", mark));
+                    }
+                }
+                None => self.set_status(format!("no container marked {}", mark)),
+            }
+        }
+    }
+
+    fn commit_move_to_workspace(&mut self) {
+        if let StateMode::MoveToWorkspace(node_id, ref mut input) = self.mode {
+            let number = input.take();
+            self.mode = StateMode::None;
+            if number.is_empty() {
+                return;
+            }
+            let command = format!("move container to workspace number {}", number);
+            if self.issue_on(node_id, &command).is_ok() {
+                self.set_status(format!("moved to workspace {}", number));
+            }
+        }
+    }
+
+    /// Run the typed command, on the selection unless it has its own criteria
+    fn commit_command(&mut self) {
+        if let StateMode::Command(ref mut input) = self.mode {
+            let input = input.take();
+            self.mode = StateMode::None;
+            if input.trim().is_empty() {
+                return;
+            }
+            // Criteria of the user's own come first
+            let command = if input.trim_start().starts_with('[') {
+                input
+            } else {
+                format!("[con_id=\"{}\"] {}", self.selected, input)
+            };
+            if is_destructive(&command) {
+                self.mode = StateMode::Confirm(Pending::Command(command));
+            } else {
+                self.run_typed_command(&command);
+            }
+        }
+    }
+
+    fn run_typed_command(&mut self, command: &str) {
+        if self.issue(command).is_ok() {
+            self.set_status(format!("ran {}", command));
+        }
+    }
+
+    fn run_pending(&mut self) {
+        match std::mem::replace(&mut self.mode, StateMode::None) {
+            StateMode::Confirm(Pending::Kill(node_id)) => {
+                let _ = self.issue_on(node_id, "kill");
+            }
+            StateMode::Confirm(Pending::MoveToScratchpad(node_id)) => {
+                if self.issue_on(node_id, "move scratchpad").is_ok() {
+                    self.refresh_after("moved to the scratchpad");
+                }
+            }
+            StateMode::Confirm(Pending::Command(command)) => self.run_typed_command(&command),
+            mode => self.mode = mode,
+        }
+    }
+
+    /// First of `candidates`, in tree order, after the selection, wrapping around
+    fn next_of(&self, candidates: &[NodeId]) -> Option<NodeId> {
+        let position = self.selected_index();
+        candidates
+            .iter()
+            .find(|id| self.node_index.get(*id).copied() > position)
+            .or_else(|| candidates.first())
+            .copied()
+    }
+
+    fn select_next_match(&mut self) {
+        if let Some(selected) = self.next_of(&self.matches) {
+            self.selected = selected
+        }
+    }
+
+    fn select_parent(&mut self) {
+        if let Some(parent) = find_parent(self.view_root(), self.selected).map(|node| node.id) {
+            self.select_visible(parent)
+        }
+    }
+
+    fn select_first_child(&mut self) {
+        // Children as the view has them, compact or not
+        let child = find_node(self.view_root(), self.selected)
+            .and_then(|node| node.nodes.iter().chain(&node.floating_nodes).next());
+        if let Some(child) = child.map(|child| child.id) {
+            self.reveal(child);
+            self.selected = child
+        }
+    }
+
+    fn select_next_urgent(&mut self) {
+        let urgent: Vec<NodeId> = self
+            .node_ids
+            .iter()
+            .copied()
+            .filter(|id| find_node(&self.node_tree, *id).is_some_and(|node| node.urgent))
+            .collect();
+        match self.next_of(&urgent) {
+            Some(selected) => self.selected = selected,
+            None => self.set_status("no urgent window".to_string()),
+        }
+    }
+
+    fn select_previous_match(&mut self) {
+        let position = self.selected_index();
+        let previous = self
+            .matches
+            .iter()
+            .rev()
+            .find(|id| self.node_index.get(*id).copied() < position)
+            .or_else(|| self.matches.last());
+        if let Some(selected) = previous {
+            self.selected = *selected
+        }
+    }
+
+    fn swap_mode(&mut self) {
+        match self.mode {
+            StateMode::None => self.mode = StateMode::Swap(self.selected),
+            _ => self.mode = StateMode::None,
+        }
+    }
+
+    fn swap_with_selected(&mut self) {
+        if let StateMode::Swap(source) = self.mode {
+            self.mode = StateMode::None;
+            if source == self.selected {
+                return;
+            }
+            let command = format!(
+                "[con_id=\"{}\"] swap container with con_id {}",
+                source, self.selected
+            );
+            // Swapping again puts both back
+            if self.issue(&command).is_ok() {
+                self.record_undo(command);
+            }
+        }
+    }
+
+    /// Keep the command undoing the one that just ran
+    fn record_undo(&mut self, command: String) {
+        self.undo.push(command);
+        if self.undo.len() > UNDO_DEPTH {
+            self.undo.remove(0);
+        }
+    }
+
+    fn undo(&mut self) {
+        match self.undo.pop() {
+            Some(command) => {
+                if self.issue(&command).is_ok() {
+                    self.set_status(format!("undone with {}", command));
+                }
+            }
+            None => self.set_status("nothing to undo".to_string()),
+        }
+    }
+
+    fn move_mode(&mut self) {
+        match self.mode {
+            StateMode::None => self.mode = StateMode::Move(self.selected),
+            _ => self.mode = StateMode::None,
+        }
+    }
+
+    fn resize_mode(&mut self) {
+        match self.mode {
+            StateMode::None => self.mode = StateMode::Resize(self.selected),
+            _ => self.mode = StateMode::None,
+        }
+    }
+
+    fn kill_mode(&mut self) {
+        match self.mode {
+            StateMode::None => self.mode = StateMode::Confirm(Pending::Kill(self.selected)),
+            _ => self.mode = StateMode::None,
+        }
+    }
+
+    /// Sway and i3 4.22 handle gaps, older i3 only when patched
+    fn gaps_supported(&self) -> bool {
+        self.options.gaps || self.message_port.supports_gaps()
+    }
+
+    fn gaps_mode(&mut self) {
+        match self.mode {
+            StateMode::None if self.gaps_supported() => self.mode = StateMode::Gaps,
+            StateMode::None => {
+                self.set_status("set gaps = true in [options] to change gaps".to_string())
+            }
+            _ => self.mode = StateMode::None,
+        }
+    }
+
+    /// `kind` is `inner` or `outer`, `change` is `plus` or `minus`
+    fn adjust_gaps(&mut self, kind: &str, change: &str) {
+        if let StateMode::Gaps = self.mode {
+            let command = format!("gaps {} current {} {}", kind, change, GAPS_STEP);
+            // Gaps changes come with no event, fetch the new values
+            if self.issue(&command).is_ok() {
+                self.refresh_after(&format!("{} gaps {}", kind, change));
+            }
+        }
+    }
+
+    /// Inner and outer gaps of the focused workspace, when i3 reports them
+    fn current_gaps(&self) -> Option<(i64, i64)> {
+        let workspace = focused_workspace(&self.node_tree)?;
+        self.node_extras.get(&workspace.id)?.gaps
+    }
+
+    /// Whether `id` sits in a floating container, rather than in the tiling tree
+    fn is_floating(&self, id: NodeId) -> bool {
+        path_to(&self.node_tree, id)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|id| find_node(&self.node_tree, id))
+            .any(|node| node.nodetype == NodeType::FloatingCon)
+    }
+
+    /// Move a tiled container in the tree, a floating one on the screen
+    fn move_container(&mut self, direction: &str) {
+        if let StateMode::Move(node_id) = self.mode {
+            let step = if self.is_floating(node_id) {
+                format!(" {}px", FLOATING_STEP)
+            } else {
+                String::new()
+            };
+            if self
+                .issue_on(node_id, &format!("move {}{}", direction, step))
+                .is_err()
+            {
+                return;
+            }
+            if let Some(back) = opposite(direction) {
+                self.record_undo(format!("[con_id=\"{}\"] move {}{}", node_id, back, step));
+            }
+        }
+    }
+
+    fn center_floating(&mut self) {
+        if let StateMode::Move(node_id) = self.mode {
+            if self.is_floating(node_id) {
+                let _ = self.issue_on(node_id, "move position center");
+            } else {
+                self.set_status("only floating windows can be centered".to_string());
+            }
+        }
+    }
+
+    /// `change` is `grow` or `shrink`, `dimension` is `width` or `height`
+    fn resize_container(&mut self, change: &str, dimension: &str) {
+        if let StateMode::Resize(node_id) = self.mode {
+            // Only containers inside a workspace have a size i3 can change
+            let resizable = find_node(&self.node_tree, node_id).is_some_and(|node| {
+                node.nodetype == NodeType::Con || node.nodetype == NodeType::FloatingCon
+            });
+            if !resizable {
+                return;
+            }
+            let _ = self.issue_on(
+                node_id,
+                &format!(
+                    "resize {} {} {} px or {} ppt",
+                    change, dimension, RESIZE_STEP, RESIZE_STEP
+                ),
+            );
+        }
+    }
+
+    /// Process owning the window of `node`, as Sway reports it or else as
+    /// `look_up_pid` found it
+    fn window_pid(&self, node: &Node) -> Option<u32> {
+        if let Some(pid) = self.node_extras.get(&node.id).and_then(|extra| extra.pid) {
+            return Some(pid);
+        }
+        self.x11_pids.get(&node.window?).copied().flatten()
+    }
+
+    /// Ask `xprop` for the process of the X11 window the details pane or the
+    /// kill confirmation shows, before rendering rather than while drawing
+    fn look_up_pid(&mut self) {
+        let id = match self.mode {
+            StateMode::Confirm(Pending::Kill(id)) => id,
+            _ if self.show_details => self.selected,
+            _ => return,
+        };
+        // Sway tells the process of each window already
+        if self
+            .node_extras
+            .get(&id)
+            .and_then(|extra| extra.pid)
+            .is_some()
+        {
+            return;
+        }
+        if let Some(window) = find_node(&self.node_tree, id).and_then(|node| node.window) {
+            self.x11_pids
+                .entry(window)
+                .or_insert_with(|| pid::x11_pid(window));
+        }
+    }
+
+    /// Select the container i3 focuses, once
+    fn select_focused(&mut self) {
+        match focused_id(&self.node_tree) {
+            Some(focused) => {
+                self.reveal(focused);
+                self.select_visible(focused);
+                if self.selected != focused {
+                    self.set_status("the focused container is out of view".to_string());
+                }
+            }
+            None => self.set_status("nothing is focused".to_string()),
+        }
+    }
+
+    fn toggle_follow_focus(&mut self) {
+        self.follow_focus = !self.follow_focus;
+        if self.follow_focus {
+            self.live_focus = false;
+        }
+        if let Some(focused) = focused_id(&self.node_tree).filter(|_| self.follow_focus) {
+            self.reveal(focused);
+            self.select_visible(focused);
+        }
+    }
+
+    fn focus_command(&self) -> String {
+        let output = find_node(&self.node_tree, self.selected)
+            .filter(|node| node.nodetype == NodeType::Output)
+            .and_then(|node| node.name.clone());
+        match output {
+            // Outputs can't be focused by con_id
+            Some(name) => format!("focus output \"{}\"", name),
+            None => format!("[con_id=\"{}\"] focus", self.selected),
+        }
+    }
+
+    fn focus_selected(&mut self) {
+        let _ = self.issue(&self.focus_command());
+        self.update_tree();
+    }
+
+    fn floating_toggle(&mut self) {
+        let _ = self.issue_on(self.selected, "floating toggle");
+    }
+
+    fn fullscreen_toggle(&mut self) {
+        let _ = self.issue_on(self.selected, "fullscreen toggle");
+    }
+
+    /// Split the selection `h`, `v` or `toggle`, reporting the layout it ends up in
+    fn split(&mut self, direction: &str) {
+        if self
+            .issue_on(self.selected, &format!("split {}", direction))
+            .is_err()
+        {
+            return;
+        }
+        self.update_tree();
+        if !self.disconnected {
+            match self.layout_target() {
+                Some(node) => {
+                    let layout = format!("layout {:?}", node.layout);
+                    self.set_status(layout)
+                }
+                None => self.clear_status(),
+            }
+        }
+    }
+
+    /// Show the selected scratchpad window, or hide it back
+    fn scratchpad_show(&mut self) {
+        if self.issue_on(self.selected, "scratchpad show").is_ok() {
+            self.refresh_after("toggled the scratchpad window");
+        }
+    }
+
+    /// Ask before hiding the selection in the scratchpad
+    fn move_to_scratchpad(&mut self) {
+        self.mode = StateMode::Confirm(Pending::MoveToScratchpad(self.selected));
+    }
+
+    /// Fetch the moved windows right away and report `done`
+    fn refresh_after(&mut self, done: &str) {
+        self.update_tree();
+        if !self.disconnected {
+            self.set_status(done.to_string());
+        }
+    }
+
+    fn split_toggle(&mut self) {
+        self.split("toggle");
+    }
+
+    fn split_horizontal(&mut self) {
+        self.split("h");
+    }
+
+    fn split_vertical(&mut self) {
+        self.split("v");
+    }
+
+    /// Container whose layout changes when setting the layout of the selection,
+    /// i3 applies it to the parent of anything but workspaces
+    fn layout_target(&self) -> Option<&Node> {
+        let node = find_node(&self.node_tree, self.selected)?;
+        if node.nodetype == NodeType::Workspace {
+            return Some(node);
+        }
+        let path = path_to(&self.node_tree, self.selected)?;
+        let parent = path.get(path.len().checked_sub(2)?)?;
+        find_node(&self.node_tree, *parent)
+    }
+
+    fn cycle_layout(&mut self) {
+        let next = match self.layout_target().map(|node| &node.layout) {
+            Some(NodeLayout::SplitH) => "splitv",
+            Some(NodeLayout::SplitV) => "tabbed",
+            Some(NodeLayout::Tabbed) => "stacking",
+            Some(NodeLayout::Stacked) => "splith",
+            _ => {
+                self.set_status("the layout here can't be cycled".to_string());
+                return;
+            }
+        };
+        // A failure is already in the status
+        if self
+            .issue_on(self.selected, &format!("layout {}", next))
+            .is_ok()
+        {
+            self.refresh_after(&format!("layout {}", next));
+        }
+    }
+
+    fn layout_mode(&mut self) {
+        match self.mode {
+            StateMode::None => self.mode = StateMode::Layout,
+            _ => self.mode = StateMode::None,
+        }
+    }
+
+    /// `layout` is one of the `layout` command takes
+    fn set_layout(&mut self, layout: &str) {
+        if let StateMode::Layout = self.mode {
+            let _ = self.issue_on(self.selected, &format!("layout {}", layout));
+        }
+    }
+
+    /// Give the selection and its siblings equal shares of their parent. i3 has
+    /// no command for it, each but the last one is set to its share in turn,
+    /// the last one gets what is left, a percent more or less
+    fn equalize(&mut self) {
+        if let StateMode::Layout = self.mode {
+            let (dimension, children) = match self.layout_target() {
+                Some(parent) => match parent.layout {
+                    NodeLayout::SplitH => ("width", &parent.nodes),
+                    NodeLayout::SplitV => ("height", &parent.nodes),
+                    _ => {
+                        self.set_status("only split containers share their space".to_string());
+                        return;
+                    }
+                },
+                None => return,
+            };
+            if children.len() < 2 {
+                self.set_status("nothing to equalize".to_string());
+                return;
+            }
+            let share = 100 / children.len();
+            let command = children[..children.len() - 1]
+                .iter()
+                .map(|child| {
+                    format!(
+                        "[con_id=\"{}\"] resize set {} {} ppt",
+                        child.id, dimension, share
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("; ");
+            let count = children.len();
+            if self.issue(&command).is_ok() {
+                self.set_status(format!("{} containers equalized", count));
+            }
+        }
+    }
+
+    /// Step the selected container through normal, 1 pixel and no border
+    fn cycle_border(&mut self) {
+        let next = match find_node(&self.node_tree, self.selected).map(|node| &node.border) {
+            Some(NodeBorder::Normal) => "pixel 1",
+            Some(NodeBorder::Pixel) => "none",
+            Some(_) => "normal",
+            None => return,
+        };
+        // i3 sends no event for border changes
+        if self
+            .issue_on(self.selected, &format!("border {}", next))
+            .is_ok()
+        {
+            self.refresh_after(&format!("border {}", next));
+        }
+    }
+}
+
+/// First key bound to `quit`, the input thread stops reading after it
+fn exit_key(keymap: &Keymap) -> Key {
+    keymap
+        .keys(Action::Quit)
+        .first()
+        .copied()
+        .unwrap_or(Key::Null)
+}
+
+/// Run the TUI, or whatever else the command line asks for
+pub fn run() -> Result<(), Box<dyn Error>> {
+    let args = cli::Args::parse(env::args().skip(1))?;
+    if args.help {
+        println!("{}", cli::USAGE);
+        return Ok(());
+    }
+    if let Some(socket) = &args.socket {
+        backend::use_socket(socket);
+    }
+    if let Some(log) = &args.log {
+        logger::init(log.as_ref())?;
+    }
+    let config = Config::load()?;
+    if args.print_tree {
+        let state = State::new(config)?;
+        return Ok(ui::print_tree(&state, &mut io::stdout())?);
+    }
+    let i3_event_listener = I3EventListener::connect()?;
+    let mut events_config = event::Config {
+        exit_key: exit_key(&config.keymap),
+        ..event::Config::default()
+    };
+    if let Some(tick_rate) = args.tick_rate {
+        events_config.tick_rate = tick_rate;
+    }
+    if let Some(names) = &args.subscribe {
+        // Checked when parsed
+        events_config.subscriptions = names
+            .iter()
+            .filter_map(|name| cli::subscription(name))
+            .collect();
+    }
+    let mut state = State::new(config)?;
+    state.dry_run = args.dry_run;
+    state.no_background = args.no_bg;
+    if let Some(id) = persist::load_selection() {
+        // Unless it went away since
+        state.select_visible(id);
+    }
+    let mut events = Events::with_config(events_config, i3_event_listener);
+    let mut renderer = ui::Renderer::new()?;
+
+    // Ticks alone leave the frame as it is
+    let mut dirty = true;
+    loop {
+        if dirty {
+            state.look_up_pid();
+            renderer.render(&state)?;
+        }
+        dirty = true;
+
+        let event = match events.next_until(state.live_focus_at)? {
+            Some(event) => event,
+            None => {
+                state.live_focus();
+                continue;
+            }
+        };
+        match event {
+            // The input thread stops reading after it, whatever the mode
+            Event::Input(input) if events.is_exit_key(input) => break,
+            Event::Input(input) => match state.mode {
+                StateMode::None => {
+                    if state.push_count(input) {
+                        continue;
+                    }
+                    let count = state.take_count();
+                    match state.keymap.action(input) {
+                        Some(Action::Quit) => {
+                            break;
+                        }
+                        Some(Action::SelectNext) => (0..count).for_each(|_| state.select_next()),
+                        Some(Action::SelectPrevious) => {
+                            (0..count).for_each(|_| state.select_previous())
+                        }
+                        Some(Action::SelectFirst) => state.select_first(),
+                        Some(Action::SelectLast) => state.select_last(),
+                        Some(Action::SelectParent) => {
+                            (0..count).for_each(|_| state.select_parent())
+                        }
+                        Some(Action::SelectFirstChild) => {
+                            (0..count).for_each(|_| state.select_first_child())
+                        }
+                        Some(Action::Focus) => state.focus_selected(),
+                        Some(Action::Search) => state.search_mode(),
+                        Some(Action::NextMatch) => {
+                            (0..count).for_each(|_| state.select_next_match())
+                        }
+                        Some(Action::PreviousMatch) => {
+                            (0..count).for_each(|_| state.select_previous_match())
+                        }
+                        Some(Action::ClearSearch) => state.cancel_search(),
+                        Some(Action::MoveMode) => state.move_mode(),
+                        Some(Action::ResizeMode) => state.resize_mode(),
+                        Some(Action::GapsMode) => state.gaps_mode(),
+                        Some(Action::LayoutMode) => state.layout_mode(),
+                        Some(Action::Swap) => state.swap_mode(),
+                        Some(Action::Undo) => state.undo(),
+                        Some(Action::MoveToWorkspace) => state.move_to_workspace_mode(),
+                        Some(Action::SplitToggle) => state.split_toggle(),
+                        Some(Action::SplitHorizontal) => state.split_horizontal(),
+                        Some(Action::SplitVertical) => state.split_vertical(),
+                        Some(Action::CycleLayout) => state.cycle_layout(),
+                        Some(Action::CycleBorder) => state.cycle_border(),
+                        Some(Action::Kill) => state.kill_mode(),
+                        Some(Action::Help) => state.mode = StateMode::Help,
+                        Some(Action::ExportJson) => state.export_json(),
+                        Some(Action::ExportDot) => state.export_dot(),
+                        Some(Action::CopyId) => state.copy_id(),
+                        Some(Action::Command) => {
+                            state.mode = StateMode::Command(InputField::default())
+                        }
+                        Some(Action::Mark) => state.mode = StateMode::Mark(InputField::default()),
+                        Some(Action::FollowFocus) => state.toggle_follow_focus(),
+                        Some(Action::LiveFocus) => state.toggle_live_focus(),
+                        Some(Action::SelectFocused) => state.select_focused(),
+                        Some(Action::Details) => state.show_details = !state.show_details,
+                        Some(Action::RawJson) => state.raw_json_mode(),
+                        Some(Action::ShowClass) => state.show_class = !state.show_class,
+                        Some(Action::Counts) => {
+                            state.show_counts = !state.show_counts;
+                            state.tree_generation += 1;
+                        }
+                        Some(Action::CycleOutput) => state.cycle_output(),
+                        Some(Action::WorkspaceOnly) => state.toggle_workspace_only(),
+                        Some(Action::Compact) => state.toggle_compact(),
+                        Some(Action::Collapse) => state.toggle_collapse(),
+                        Some(Action::ScratchpadShow) => state.scratchpad_show(),
+                        Some(Action::Jump) => state.jump_mode(),
+                        Some(Action::MoveToScratchpad) => state.move_to_scratchpad(),
+                        Some(Action::Workspaces) => state.workspaces_mode(),
+                        Some(Action::Marks) => state.marks_mode(),
+                        Some(Action::Reload) => {
+                            state.reload_config();
+                            events.set_exit_key(exit_key(&state.keymap));
+                        }
+                        Some(Action::CollapseAll) => state.collapse_all(),
+                        Some(Action::ExpandAll) => state.expand_all(),
+                        Some(Action::Clock) => state.show_clock = !state.show_clock,
+                        Some(Action::NextUrgent) => {
+                            (0..count).for_each(|_| state.select_next_urgent())
+                        }
+                        Some(Action::FullscreenToggle) => state.fullscreen_toggle(),
+                        Some(Action::FloatingToggle) => state.floating_toggle(),
+                        None => {}
+                    }
+                }
+                StateMode::Search(_) => match input {
+                    Key::Esc => state.cancel_search(),
+                    Key::Left => state.prompt_left(),
+                    Key::Right => state.prompt_right(),
+                    Key::Char('\n') => state.commit_search(),
+                    Key::Backspace => state.prompt_pop(),
+                    Key::Char(c) => state.prompt_push(c),
+                    _ => {}
+                },
+                StateMode::Command(_) => match input {
+                    Key::Esc => state.mode = StateMode::None,
+                    Key::Left => state.prompt_left(),
+                    Key::Right => state.prompt_right(),
+                    Key::Char('\n') => state.commit_command(),
+                    Key::Backspace => state.prompt_pop(),
+                    Key::Char(c) => state.prompt_push(c),
+                    _ => {}
+                },
+                StateMode::Mark(_) => match input {
+                    Key::Esc => state.mode = StateMode::None,
+                    Key::Left => state.prompt_left(),
+                    Key::Right => state.prompt_right(),
+                    Key::Char('\n') => state.commit_mark(),
+                    Key::Backspace => state.prompt_pop(),
+                    Key::Char(c) => state.prompt_push(c),
+                    _ => {}
+                },
+                StateMode::MoveToWorkspace(_, _) => match input {
+                    Key::Esc => state.mode = StateMode::None,
+                    Key::Left => state.prompt_left(),
+                    Key::Right => state.prompt_right(),
+                    Key::Char('\n') => state.commit_move_to_workspace(),
+                    Key::Backspace => state.prompt_pop(),
+                    Key::Char(c) if c.is_ascii_digit() => state.prompt_push(c),
+                    _ => {}
+                },
+                StateMode::Jump(_, _) => match input {
+                    Key::Esc => state.mode = StateMode::None,
+                    Key::Backspace => state.prompt_pop(),
+                    Key::Char(c) => state.jump_push(c),
+                    _ => {}
+                },
+                StateMode::Workspaces(_) => match input {
+                    Key::Esc => state.workspaces_mode(),
+                    Key::Char('\n') => state.switch_workspace(),
+                    _ => match state.keymap.action(input) {
+                        Some(Action::Quit) => {
+                            break;
+                        }
+                        Some(Action::Workspaces) => state.workspaces_mode(),
+                        Some(Action::SelectNext) => state.workspaces_step(true),
+                        Some(Action::SelectPrevious) => state.workspaces_step(false),
+                        _ => {}
+                    },
+                },
+                StateMode::Marks(_) => match input {
+                    Key::Esc => state.marks_mode(),
+                    Key::Char('\n') => state.select_mark(),
+                    _ => match state.keymap.action(input) {
+                        Some(Action::Quit) => {
+                            break;
+                        }
+                        Some(Action::Marks) => state.marks_mode(),
+                        Some(Action::SelectNext) => state.marks_step(true),
+                        Some(Action::SelectPrevious) => state.marks_step(false),
+                        _ => {}
+                    },
+                },
+                StateMode::RawJson(_) => match input {
+                    Key::Esc => state.raw_json_mode(),
+                    Key::PageDown => state.raw_json_page(true),
+                    Key::PageUp => state.raw_json_page(false),
+                    _ => match state.keymap.action(input) {
+                        Some(Action::Quit) => {
+                            break;
+                        }
+                        Some(Action::RawJson) => state.raw_json_mode(),
+                        Some(Action::SelectNext) => state.raw_json_step(true),
+                        Some(Action::SelectPrevious) => state.raw_json_step(false),
+                        _ => {}
+                    },
+                },
+                StateMode::Help => state.mode = StateMode::None,
+                StateMode::Confirm(_) => match input {
+                    Key::Char('y') => state.run_pending(),
+                    _ => state.mode = StateMode::None,
+                },
+                StateMode::Move(_node_id) => match input {
+                    Key::Esc => state.move_mode(),
+                    Key::Down => state.move_container("down"),
+                    Key::Up => state.move_container("up"),
+                    Key::Left => state.move_container("left"),
+                    Key::Right => state.move_container("right"),
+                    Key::Char('c') => state.center_floating(),
+                    _ => match state.keymap.action(input) {
+                        Some(Action::Quit) => {
+                            break;
+                        }
+                        Some(Action::MoveMode) => state.move_mode(),
+                        Some(Action::Undo) => state.undo(),
+                        Some(Action::SplitToggle) => state.split_toggle(),
+                        Some(Action::SplitHorizontal) => state.split_horizontal(),
+                        Some(Action::SplitVertical) => state.split_vertical(),
+                        _ => {}
+                    },
+                },
+                StateMode::Swap(_node_id) => match input {
+                    Key::Esc => state.swap_mode(),
+                    _ => match state.keymap.action(input) {
+                        Some(Action::Quit) => {
+                            break;
+                        }
+                        Some(Action::Swap) => state.swap_with_selected(),
+                        Some(Action::SelectNext) => state.select_next(),
+                        Some(Action::SelectPrevious) => state.select_previous(),
+                        Some(Action::SelectFirst) => state.select_first(),
+                        Some(Action::SelectLast) => state.select_last(),
+                        Some(Action::SelectParent) => state.select_parent(),
+                        Some(Action::SelectFirstChild) => state.select_first_child(),
+                        _ => {}
+                    },
+                },
+                StateMode::Resize(_node_id) => match input {
+                    Key::Esc => state.resize_mode(),
+                    Key::Down => state.resize_container("grow", "height"),
+                    Key::Up => state.resize_container("shrink", "height"),
+                    Key::Left => state.resize_container("shrink", "width"),
+                    Key::Right => state.resize_container("grow", "width"),
+                    _ => match state.keymap.action(input) {
+                        Some(Action::Quit) => {
+                            break;
+                        }
+                        Some(Action::ResizeMode) => state.resize_mode(),
+                        _ => {}
+                    },
+                },
+                StateMode::Gaps => match input {
+                    Key::Esc => state.gaps_mode(),
+                    Key::Up => state.adjust_gaps("inner", "plus"),
+                    Key::Down => state.adjust_gaps("inner", "minus"),
+                    Key::Right => state.adjust_gaps("outer", "plus"),
+                    Key::Left => state.adjust_gaps("outer", "minus"),
+                    _ => match state.keymap.action(input) {
+                        Some(Action::Quit) => {
+                            break;
+                        }
+                        Some(Action::GapsMode) => state.gaps_mode(),
+                        _ => {}
+                    },
+                },
+                StateMode::Layout => match input {
+                    Key::Esc => state.layout_mode(),
+                    Key::Char('e') => state.equalize(),
+                    Key::Char('h') => state.set_layout("splith"),
+                    Key::Char('v') => state.set_layout("splitv"),
+                    Key::Char('t') => state.set_layout("tabbed"),
+                    Key::Char('s') => state.set_layout("stacking"),
+                    _ => match state.keymap.action(input) {
+                        Some(Action::Quit) => {
+                            break;
+                        }
+                        Some(Action::LayoutMode) => state.layout_mode(),
+                        _ => {}
+                    },
+                },
+            },
+            Event::I3 => {
+                state.i3_event_count += 1;
+                state.update_tree();
+            }
+            // Terminal coordinates start at 1
+            Event::Mouse(MouseEvent::Press(MouseButton::Left, column, row)) => {
+                let index = renderer.row_at(column.saturating_sub(1), row.saturating_sub(1));
+                match (index, &state.mode) {
+                    (Some(index), StateMode::None) => state.click(index, Instant::now()),
+                    _ => dirty = false,
+                }
+            }
+            Event::Mouse(MouseEvent::Press(button @ MouseButton::WheelUp, column, row))
+            | Event::Mouse(MouseEvent::Press(button @ MouseButton::WheelDown, column, row)) => {
+                let over = renderer.in_tree(column.saturating_sub(1), row.saturating_sub(1));
+                match state.mode {
+                    StateMode::None if over => state.wheel(button == MouseButton::WheelDown),
+                    _ => dirty = false,
+                }
+            }
+            Event::Mouse(_) => dirty = false,
+            Event::Mode(mode) => state.binding_mode = mode,
+            Event::I3Disconnected => {
+                state.set_sticky_status("i3 events lost, reconnecting…".to_string())
+            }
+            // The tree connection may be lost too, on `i3 restart`
+            Event::I3Reconnected if state.disconnected => {
+                state.reconnect();
+            }
+            Event::I3Reconnected => {
+                state.clear_status();
+                state.update_tree();
+            }
+            // The clock moves on, old messages go
+            Event::Tick => {
+                let expired = state.expire_status(Instant::now());
+                dirty = state.reconnect() || expired || state.show_clock
+            }
+            // Rendered again right away with the new frame size
+            Event::Resize(_, _) => {}
+        }
+
+        // Typed queries may contain the exit key
+        match state.mode {
+            StateMode::Search(_)
+            | StateMode::Command(_)
+            | StateMode::Mark(_)
+            | StateMode::Jump(_, _) => events.disable_exit_key(),
+            _ => events.enable_exit_key(),
+        }
+    }
+    if let Err(err) = persist::save_selection(state.selected) {
+        log::warn!("selection not saved: {}", err);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::backend::mock::MockBackend;
+    use crate::bench::node;
+
+    /// State over a mock tree, with the log of the commands it sends
+    fn mock_state(tree: Node) -> (State, Rc<RefCell<Vec<String>>>) {
+        let backend = MockBackend::new(tree);
+        let commands = Rc::clone(&backend.commands);
+        let state = State::with_backend(Box::new(backend), Config::default()).unwrap();
+        (state, commands)
+    }
+
+    #[test]
+    fn move_container_targets_the_captured_id() {
+        let (mut state, commands) = mock_state(node(1, vec![node(2, vec![])]));
+        state.select_next();
+        state.move_mode();
+        state.move_container("left");
+        assert_eq!(*commands.borrow(), vec![r#"[con_id="2"] move left"#]);
+    }
+
+    #[test]
+    fn split_reports_the_resulting_layout() {
+        let (mut state, commands) = mock_state(node(1, vec![node(2, vec![])]));
+        state.select_next();
+        state.split_vertical();
+        assert_eq!(*commands.borrow(), vec![r#"[con_id="2"] split v"#]);
+        assert_eq!(state.status.as_deref(), Some("layout SplitH"));
+    }
+
+    #[test]
+    fn borders_cycle_from_the_current_style() {
+        let mut window = node(2, vec![]);
+        window.border = NodeBorder::Pixel;
+        let (mut state, commands) = mock_state(node(1, vec![window]));
+        state.select_next();
+        state.cycle_border();
+        assert_eq!(*commands.borrow(), vec![r#"[con_id="2"] border none"#]);
+        assert_eq!(state.status.as_deref(), Some("border none"));
+    }
+
+    #[test]
+    fn ticks_leave_a_connected_state_alone() {
+        let (mut state, _) = mock_state(node(1, vec![]));
+        assert!(!state.reconnect());
+    }
+
+    #[test]
+    fn destructive_commands_are_spotted() {
+        assert!(is_destructive("kill"));
+        assert!(is_destructive(r#"[class="Firefox"] kill"#));
+        assert!(is_destructive("focus left; exit"));
+        assert!(is_destructive(
+            r#"[con_id="2"] move container to scratchpad"#
+        ));
+        assert!(!is_destructive(r#"[title="kill"] focus"#));
+        assert!(!is_destructive("move left, layout tabbed"));
+    }
+
+    #[test]
+    fn destructive_commands_wait_for_confirmation() {
+        let (mut state, commands) = mock_state(node(1, vec![node(2, vec![])]));
+        state.select_next();
+        state.mode = StateMode::Command(InputField::from("kill"));
+        state.commit_command();
+        assert!(commands.borrow().is_empty());
+        assert!(matches!(
+            state.mode,
+            StateMode::Confirm(Pending::Command(_))
+        ));
+        state.run_pending();
+        assert_eq!(*commands.borrow(), vec![r#"[con_id="2"] kill"#]);
+        assert!(matches!(state.mode, StateMode::None));
+    }
+
+    #[test]
+    fn typed_commands_run_on_the_selection_unless_they_start_with_criteria() {
+        let (mut state, commands) = mock_state(node(1, vec![node(2, vec![])]));
+        state.select_next();
+        for typed in &["rename workspace to [web]", r#" [class="URxvt"] focus"#] {
+            state.mode = StateMode::Command(InputField::from(*typed));
+            state.commit_command();
+        }
+        assert_eq!(
+            *commands.borrow(),
+            vec![
+                r#"[con_id="2"] rename workspace to [web]"#,
+                r#" [class="URxvt"] focus"#
+            ]
+        );
+    }
+
+    #[test]
+    fn kill_targets_the_container_selected_at_first() {
+        let (mut state, commands) = mock_state(node(1, vec![node(2, vec![])]));
+        state.kill_mode();
+        state.select_next();
+        state.run_pending();
+        assert_eq!(*commands.borrow(), vec![r#"[con_id="1"] kill"#]);
+    }
+
+    #[test]
+    fn scratchpad_show_targets_the_selection() {
+        let (mut state, commands) = mock_state(node(1, vec![node(2, vec![])]));
+        state.select_next();
+        state.scratchpad_show();
+        assert_eq!(*commands.borrow(), vec![r#"[con_id="2"] scratchpad show"#]);
+        assert_eq!(
+            state.status.as_deref(),
+            Some("toggled the scratchpad window")
+        );
+    }
+
+    #[test]
+    fn move_to_scratchpad_waits_for_confirmation() {
+        let (mut state, commands) = mock_state(node(1, vec![node(2, vec![])]));
+        state.select_next();
+        state.move_to_scratchpad();
+        assert!(commands.borrow().is_empty());
+        state.run_pending();
+        assert_eq!(*commands.borrow(), vec![r#"[con_id="2"] move scratchpad"#]);
+        assert_eq!(state.status.as_deref(), Some("moved to the scratchpad"));
+    }
+
+    #[test]
+    fn labels_are_prefix_free() {
+        assert_eq!(jump_labels(3), vec!["a", "s", "d"]);
+        let labels = jump_labels(30);
+        assert_eq!(labels.len(), 30);
+        assert!(labels.iter().all(|label| label.len() == 2));
+        assert_eq!(labels.iter().collect::<HashSet<_>>().len(), 30);
+    }
+
+    #[test]
+    fn jump_selects_the_labelled_row() {
+        let (mut state, _) = mock_state(node(1, (2..=30).map(|id| node(id, vec![])).collect()));
+        state.jump_mode();
+        state.jump_push('a');
+        assert!(matches!(state.mode, StateMode::Jump(_, ref typed) if typed.text() == "a"));
+        // No label starts with `a1`
+        state.jump_push('1');
+        assert!(matches!(state.mode, StateMode::Jump(_, ref typed) if typed.text() == "a"));
+        state.jump_push('d');
+        assert!(matches!(state.mode, StateMode::None));
+        assert_eq!(state.selected, 3);
+    }
+
+    #[test]
+    fn counts_accumulate_digits() {
+        let (mut state, _) = mock_state(node(1, vec![]));
+        assert!(!state.push_count(Key::Char('0')));
+        assert!(state.push_count(Key::Char('1')));
+        assert!(state.push_count(Key::Char('0')));
+        assert!(!state.push_count(Key::Char('j')));
+        assert_eq!(state.take_count(), 10);
+        assert_eq!(state.take_count(), 1);
+    }
+
+    #[test]
+    fn floating_windows_move_on_the_screen() {
+        let mut floating = node(2, vec![node(3, vec![])]);
+        floating.nodetype = NodeType::FloatingCon;
+        let mut workspace = node(1, vec![node(4, vec![])]);
+        workspace.floating_nodes = vec![floating];
+        let (mut state, commands) = mock_state(workspace);
+        state.selected = 3;
+        state.move_mode();
+        state.move_container("up");
+        state.center_floating();
+        state.mode = StateMode::Move(4);
+        state.center_floating();
+        assert_eq!(
+            *commands.borrow(),
+            vec![
+                r#"[con_id="3"] move up 20px"#,
+                r#"[con_id="3"] move position center"#
+            ]
+        );
+        assert_eq!(state.undo, vec![r#"[con_id="3"] move down 20px"#]);
+        assert_eq!(
+            state.status.as_deref(),
+            Some("only floating windows can be centered")
+        );
+    }
+
+    #[test]
+    fn move_container_outside_move_mode_does_nothing() {
+        let (mut state, commands) = mock_state(node(1, vec![]));
+        state.move_container("left");
+        assert!(commands.borrow().is_empty());
+    }
+
+    #[test]
+    fn resize_skips_workspaces() {
+        let mut workspace = node(2, vec![]);
+        workspace.nodetype = NodeType::Workspace;
+        let (mut state, commands) = mock_state(node(1, vec![workspace]));
+        state.select_next();
+        state.resize_mode();
+        state.resize_container("grow", "width");
+        assert!(commands.borrow().is_empty());
+    }
+
+    #[test]
+    fn mark_applies_to_the_selection() {
+        let (mut state, commands) = mock_state(node(1, vec![node(2, vec![])]));
+        state.select_next();
+        state.mode = StateMode::Mark(InputField::from("todo"));
+        state.commit_mark();
+        assert_eq!(*commands.borrow(), vec![r#"[con_id="2"] mark todo"#]);
+    }
+
+    #[test]
+    fn follow_focus_selects_the_focused_container() {
+        let mut focused = node(3, vec![]);
+        focused.focused = true;
+        let (mut state, _) = mock_state(node(1, vec![node(2, vec![]), focused]));
+        state.toggle_follow_focus();
+        assert_eq!(state.selected, 3);
+        state.select_first();
+        state.update_tree();
+        assert_eq!(state.selected, 3);
+        state.toggle_follow_focus();
+        state.select_first();
+        state.update_tree();
+        assert_eq!(state.selected, 1);
+    }
+
+    #[test]
+    fn next_urgent_wraps_around() {
+        let urgent = |id| {
+            let mut node = node(id, vec![]);
+            node.urgent = true;
+            node
+        };
+        let (mut state, _) = mock_state(node(1, vec![urgent(2), node(3, vec![]), urgent(4)]));
+        state.select_next_urgent();
+        assert_eq!(state.selected, 2);
+        state.select_next_urgent();
+        assert_eq!(state.selected, 4);
+        state.select_next_urgent();
+        assert_eq!(state.selected, 2);
+    }
+
+    #[test]
+    fn next_urgent_reports_when_there_is_none() {
+        let (mut state, _) = mock_state(node(1, vec![node(2, vec![])]));
+        state.select_next_urgent();
+        assert_eq!(state.selected, 1);
+        assert_eq!(state.status.as_deref(), Some("no urgent window"));
+    }
+
+    #[test]
+    fn parent_and_child_navigation() {
+        let (mut state, _) = mock_state(node(
+            1,
+            vec![node(2, vec![]), node(3, vec![node(4, vec![])])],
+        ));
+        state.selected = 4;
+        state.select_parent();
+        assert_eq!(state.selected, 3);
+        state.select_parent();
+        assert_eq!(state.selected, 1);
+        state.select_parent();
+        assert_eq!(state.selected, 1);
+        state.select_first_child();
+        assert_eq!(state.selected, 2);
+        state.select_first_child();
+        assert_eq!(state.selected, 2);
+    }
+
+    /// Workspace 1 with a tiled split 2 of window 3, then floating container 4
+    /// of window 5
+    fn mixed_tree() -> Node {
+        let mut floating = node(4, vec![node(5, vec![])]);
+        floating.nodetype = NodeType::FloatingCon;
+        let mut workspace = node(1, vec![node(2, vec![node(3, vec![])])]);
+        workspace.nodetype = NodeType::Workspace;
+        workspace.floating_nodes = vec![floating];
+        workspace
+    }
+
+    #[test]
+    fn collect_ids_includes_floating_nodes() {
+        assert_eq!(
+            collect_ids(&mixed_tree(), &HashSet::new()),
+            vec![1, 2, 3, 4, 5]
+        );
+    }
+
+    #[test]
+    fn collapsing_skips_hidden_nodes() {
+        let (mut state, _) = mock_state(mixed_tree());
+        state.select_next();
+        state.toggle_collapse();
+        assert_eq!(state.node_ids, vec![1, 2, 4, 5]);
+        state.select_next();
+        assert_eq!(state.selected, 4);
+        state.select_previous();
+        state.select_first_child();
+        assert_eq!(state.selected, 3);
+        assert_eq!(state.node_ids, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn collapse_all_keeps_workspaces_in_view() {
+        let mut output = node(1, vec![mixed_tree()]);
+        output.nodetype = NodeType::Output;
+        output.nodes[0].id = 6;
+        let (mut state, _) = mock_state(output);
+        state.select_last();
+        state.collapse_all();
+        assert_eq!(state.node_ids, vec![1, 6]);
+        assert_eq!(state.selected, 6);
+        state.select_first_child();
+        assert_eq!(state.node_ids, vec![1, 6, 2, 4]);
+        state.expand_all();
+        assert_eq!(state.node_ids, vec![1, 6, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn collapsing_a_window_does_nothing() {
+        let (mut state, _) = mock_state(mixed_tree());
+        state.select_last();
+        state.toggle_collapse();
+        assert!(state.collapsed.is_empty());
+    }
+
+    #[test]
+    fn floating_nodes_are_navigable() {
+        let (mut state, _) = mock_state(mixed_tree());
+        state.select_last();
+        assert_eq!(state.selected, 5);
+        state.select_previous();
+        assert_eq!(state.selected, 4);
+        state.select_parent();
+        assert_eq!(state.selected, 1);
+    }
+
+    #[test]
+    fn swap_targets_the_selection() {
+        let (mut state, commands) = mock_state(node(1, vec![node(2, vec![]), node(3, vec![])]));
+        state.select_next();
+        state.swap_mode();
+        state.select_next();
+        state.swap_with_selected();
+        assert_eq!(
+            *commands.borrow(),
+            vec![r#"[con_id="2"] swap container with con_id 3"#]
+        );
+        assert!(matches!(state.mode, StateMode::None));
+    }
+
+    #[test]
+    fn move_to_workspace_moves_the_captured_container() {
+        let (mut state, commands) = mock_state(node(1, vec![node(2, vec![])]));
+        state.select_next();
+        state.move_to_workspace_mode();
+        state.select_first();
+        state.prompt_push('4');
+        state.prompt_push('2');
+        state.commit_move_to_workspace();
+        assert_eq!(
+            *commands.borrow(),
+            vec![r#"[con_id="2"] move container to workspace number 42"#]
+        );
+        assert_eq!(state.status.as_deref(), Some("moved to workspace 42"));
+    }
+
+    #[test]
+    fn navigation_stops_at_the_ends() {
+        let (mut state, _) = mock_state(node(1, vec![node(2, vec![])]));
+        state.select_previous();
+        assert_eq!(state.selected, 1);
+        state.select_last();
+        state.select_next();
+        assert_eq!(state.selected, 2);
+    }
+
+    #[test]
+    fn navigation_wraps_when_enabled() {
+        let (mut state, _) = mock_state(node(1, vec![node(2, vec![])]));
+        state.options.wrap_navigation = true;
+        state.select_previous();
+        assert_eq!(state.selected, 2);
+        state.select_next();
+        assert_eq!(state.selected, 1);
+    }
+
+    #[test]
+    fn step_from_first() {
+        assert_eq!(step(Some(0), 3, true, false), Some(1));
+        assert_eq!(step(Some(0), 3, false, false), Some(0));
+    }
+
+    #[test]
+    fn step_from_middle() {
+        assert_eq!(step(Some(1), 3, true, false), Some(2));
+        assert_eq!(step(Some(1), 3, false, false), Some(0));
+    }
+
+    #[test]
+    fn step_stays_on_last() {
+        assert_eq!(step(Some(2), 3, true, false), Some(2));
+        assert_eq!(step(Some(2), 3, true, true), Some(0));
+    }
+
+    #[test]
+    fn step_selects_first_when_missing() {
+        assert_eq!(step(None, 3, true, false), Some(0));
+        assert_eq!(step(None, 3, false, false), Some(0));
+        assert_eq!(step(None, 0, true, false), None);
+    }
+
+    /// `node_index` maps each of `node_ids` to its position, and nothing else
+    fn assert_index_in_sync(state: &State) {
+        assert_eq!(state.node_index, index_ids(&state.node_ids));
+    }
+
+    #[test]
+    fn index_follows_refreshes() {
+        let (mut state, _) = mock_state(mixed_tree());
+        assert_index_in_sync(&state);
+        state.select_next();
+        state.toggle_collapse();
+        assert_index_in_sync(&state);
+        assert_eq!(state.selected_index(), Some(1));
+        state.select_next();
+        assert_eq!(state.selected, 4);
+        state.update_tree();
+        assert_index_in_sync(&state);
+        assert_eq!(state.selected_index(), Some(2));
+        state.expand_all();
+        assert_index_in_sync(&state);
+        assert_eq!(state.selected_index(), Some(3));
+    }
+
+    #[test]
+    fn workspace_pane_switches_to_the_chosen_one() {
+        let mut first = node(2, vec![]);
+        first.nodetype = NodeType::Workspace;
+        let mut second = node(3, vec![]);
+        second.nodetype = NodeType::Workspace;
+        second.name = Some("2: \"web\"".to_string());
+        let (mut state, commands) = mock_state(node(1, vec![first, second]));
+        state.workspaces_mode();
+        assert!(matches!(state.mode, StateMode::Workspaces(0)));
+        state.workspaces_step(true);
+        state.switch_workspace();
+        assert_eq!(*commands.borrow(), vec![r#"workspace "2: \"web\"""#]);
+        assert!(matches!(state.mode, StateMode::None));
+    }
+
+    #[test]
+    fn applied_config_keeps_the_selection() {
+        let (mut state, commands) = mock_state(node(1, vec![node(2, vec![])]));
+        state.select_next();
+        let config = Config::from_toml("[keys]\nquit = \"Q\"").unwrap();
+        state.apply_config(config);
+        assert_eq!(state.selected, 2);
+        assert_eq!(exit_key(&state.keymap), Key::Char('Q'));
+        assert!(commands.borrow().is_empty());
+    }
+
+    #[test]
+    fn moves_are_undone_latest_first() {
+        let (mut state, commands) = mock_state(node(1, vec![node(2, vec![]), node(3, vec![])]));
+        state.select_next();
+        state.move_mode();
+        state.move_container("left");
+        state.move_container("up");
+        state.move_mode();
+        state.undo();
+        state.undo();
+        state.undo();
+        assert_eq!(
+            commands.borrow()[2..],
+            [r#"[con_id="2"] move down"#, r#"[con_id="2"] move right"#]
+        );
+        assert_eq!(state.status.as_deref(), Some("nothing to undo"));
+    }
+
+    #[test]
+    fn matches_cycle_and_follow_the_tree() {
+        let mut tree = node(1, vec![node(2, vec![]), node(3, vec![]), node(4, vec![])]);
+        tree.nodes[0].name = Some("term".to_string());
+        tree.nodes[2].name = Some("Terminal".to_string());
+        let (mut state, _) = mock_state(tree);
+        state.search_mode();
+        "term".chars().for_each(|c| state.prompt_push(c));
+        state.commit_search();
+        assert_eq!(state.matches, vec![2, 4]);
+        assert_eq!(state.selected, 2);
+        state.select_next_match();
+        assert_eq!(state.selected, 4);
+        state.select_next_match();
+        assert_eq!(state.selected, 2);
+        state.select_previous_match();
+        assert_eq!(state.selected, 4);
+        state.collapsed.insert(1);
+        state.refresh_ids();
+        assert!(state.matches.is_empty());
+        state.search_mode();
+        assert!(state.matches.is_empty());
+    }
+
+    #[test]
+    fn output_filter_cycles_and_keeps_the_selection_in_view() {
+        let mut outputs = vec![
+            node(2, vec![node(3, vec![])]),
+            node(4, vec![node(5, vec![])]),
+        ];
+        for (output, name) in outputs.iter_mut().zip(["left", "right"].iter()) {
+            output.nodetype = NodeType::Output;
+            output.name = Some(name.to_string());
+        }
+        let (mut state, _) = mock_state(node(1, outputs));
+        state.select_last();
+        state.cycle_output();
+        assert_eq!(state.output_filter.as_deref(), Some("left"));
+        assert_eq!(state.node_ids, vec![2, 3]);
+        assert_eq!(state.selected, 2);
+        state.select_parent();
+        assert_eq!(state.selected, 2);
+        state.cycle_output();
+        assert_eq!(state.node_ids, vec![4, 5]);
+        state.update_tree();
+        assert_eq!(state.node_ids, vec![4, 5]);
+        state.cycle_output();
+        assert_eq!(state.output_filter, None);
+        assert_eq!(state.node_ids, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn workspace_only_shows_the_focused_workspace() {
+        let mut window = node(4, vec![]);
+        window.focused = true;
+        let mut workspaces = vec![node(2, vec![node(3, vec![])]), node(5, vec![window])];
+        for workspace in workspaces.iter_mut() {
+            workspace.nodetype = NodeType::Workspace;
+        }
+        let (mut state, _) = mock_state(node(1, workspaces));
+        state.select_next();
+        state.toggle_workspace_only();
+        assert_eq!(state.node_ids, vec![5, 4]);
+        assert_eq!(state.selected, 5);
+        state.select_next();
+        state.toggle_workspace_only();
+        assert_eq!(state.node_ids, vec![1, 2, 3, 5, 4]);
+        assert_eq!(state.selected, 4);
+    }
+
+    #[test]
+    fn mark_pane_selects_the_marked_container() {
+        let (mut state, _) = mock_state(node(1, vec![node(2, vec![node(3, vec![])])]));
+        state.node_extras.insert(
+            3,
+            ipc::NodeExtra {
+                marks: vec!["todo".to_string()],
+                ..ipc::NodeExtra::default()
+            },
+        );
+        state.marks = vec!["gone".to_string(), "todo".to_string()];
+        state.collapsed.insert(2);
+        state.refresh_ids();
+        state.marks_mode();
+        state.select_mark();
+        assert_eq!(state.status.as_deref(), Some("no container marked gone"));
+        state.marks_mode();
+        state.marks_step(true);
+        state.select_mark();
+        assert_eq!(state.selected, 3);
+        assert!(state.collapsed.is_empty());
+    }
+
+    #[test]
+    fn gaps_need_support() {
+        let (mut state, commands) = mock_state(node(1, vec![]));
+        state.gaps_mode();
+        assert!(matches!(state.mode, StateMode::None));
+        state.options.gaps = true;
+        state.gaps_mode();
+        state.adjust_gaps("inner", "plus");
+        assert_eq!(*commands.borrow(), vec!["gaps inner current plus 5"]);
+        assert_eq!(state.status.as_deref(), Some("inner gaps plus"));
+    }
+
+    #[test]
+    fn json_pane_pages_within_the_node() {
+        let (mut state, _) = mock_state(node(1, vec![node(2, vec![])]));
+        state.raw_json_mode();
+        let last = state.raw_json().lines().count() as u16 - 1;
+        for _ in 0..100 {
+            state.raw_json_page(true);
+        }
+        assert!(matches!(state.mode, StateMode::RawJson(scroll) if scroll == last));
+        state.raw_json_step(true);
+        assert_eq!(state.selected, 2);
+        assert!(matches!(state.mode, StateMode::RawJson(0)));
+    }
+
+    #[test]
+    fn live_focus_waits_for_the_last_step() {
+        let (mut state, commands) = mock_state(node(1, vec![node(2, vec![]), node(3, vec![])]));
+        state.own_window = Some(3);
+        state.toggle_follow_focus();
+        state.toggle_live_focus();
+        assert!(!state.follow_focus);
+        state.select_next();
+        state.select_next();
+        assert!(commands.borrow().is_empty());
+        assert!(state.live_focus_at.is_some());
+        state.select_previous();
+        state.live_focus();
+        assert_eq!(
+            *commands.borrow(),
+            vec![r#"[con_id="2"] focus; [con_id="3"] focus"#]
+        );
+        assert_eq!(state.live_focus_at, None);
+    }
+
+    #[test]
+    fn dry_runs_only_show_commands() {
+        let (mut state, commands) = mock_state(node(1, vec![node(2, vec![])]));
+        state.dry_run = true;
+        state.select_next();
+        state.scratchpad_show();
+        assert!(commands.borrow().is_empty());
+        assert_eq!(
+            state.status.as_deref(),
+            Some(r#"dry run: [con_id="2"] scratchpad show"#)
+        );
+    }
+
+    #[test]
+    fn equalize_sets_all_but_the_last_sibling() {
+        let mut parent = node(2, vec![node(3, vec![]), node(4, vec![]), node(5, vec![])]);
+        parent.layout = NodeLayout::SplitV;
+        let (mut state, commands) = mock_state(node(1, vec![parent]));
+        state.selected = 4;
+        state.equalize();
+        assert!(commands.borrow().is_empty());
+        state.layout_mode();
+        state.equalize();
+        assert_eq!(
+            *commands.borrow(),
+            vec![r#"[con_id="3"] resize set height 33 ppt; [con_id="4"] resize set height 33 ppt"#]
+        );
+        assert_eq!(state.status.as_deref(), Some("3 containers equalized"));
+    }
+
+    #[test]
+    fn double_clicks_focus_the_row() {
+        let (mut state, commands) = mock_state(node(1, vec![node(2, vec![]), node(3, vec![])]));
+        let start = Instant::now();
+        state.click(1, start);
+        state.click(2, start + Duration::from_millis(100));
+        state.click(2, start + Duration::from_secs(1));
+        assert_eq!(state.selected, 3);
+        assert!(commands.borrow().is_empty());
+        state.click(2, start + Duration::from_millis(1200));
+        assert_eq!(*commands.borrow(), vec![r#"[con_id="3"] focus"#]);
+    }
+
+    #[test]
+    fn wheel_notches_step_a_few_rows() {
+        let ids = (2..10).map(|id| node(id, vec![])).collect();
+        let (mut state, _) = mock_state(node(1, ids));
+        state.wheel(true);
+        assert_eq!(state.selected, 4);
+        state.wheel(false);
+        state.wheel(false);
+        assert_eq!(state.selected, 1);
+    }
+
+    #[test]
+    fn focused_container_gets_selected_once() {
+        let mut window = node(3, vec![]);
+        window.focused = true;
+        let (mut state, _) = mock_state(node(1, vec![node(2, vec![window])]));
+        state.select_focused();
+        assert_eq!(state.selected, 3);
+        state.node_tree = node(1, vec![]);
+        state.select_focused();
+        assert_eq!(state.status.as_deref(), Some("nothing is focused"));
+    }
+
+    #[test]
+    fn compact_view_keeps_workspaces_and_windows() {
+        let mut floating = node(6, vec![node(7, vec![])]);
+        floating.nodetype = NodeType::FloatingCon;
+        let mut workspace = node(2, vec![node(3, vec![node(4, vec![]), node(5, vec![])])]);
+        workspace.nodetype = NodeType::Workspace;
+        workspace.floating_nodes = vec![floating];
+        let mut root = node(1, vec![workspace]);
+        root.nodetype = NodeType::Root;
+        let (mut state, _) = mock_state(root);
+        state.selected = 3;
+        state.toggle_compact();
+        assert_eq!(state.node_ids, vec![1, 2, 4, 5, 7]);
+        assert_eq!(state.selected, 2);
+        let workspace = &state.view_root().nodes[0];
+        assert_eq!(workspace.floating_nodes[0].id, 7);
+        state.selected = 4;
+        state.select_parent();
+        assert_eq!(state.selected, 2);
+        state.toggle_compact();
+        assert_eq!(state.node_ids, vec![1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn status_messages_clear_unless_sticky() {
+        let (mut state, _) = mock_state(node(1, vec![]));
+        let timeout = state.options.status_timeout().unwrap();
+        state.set_status("copied".to_string());
+        let set_at = state.status_set_at.unwrap();
+        assert!(!state.expire_status(set_at));
+        assert!(state.expire_status(set_at + timeout));
+        assert_eq!(state.status, None);
+        state.set_sticky_status("reconnecting…".to_string());
+        assert!(!state.expire_status(Instant::now() + timeout));
+        assert_eq!(state.status.as_deref(), Some("reconnecting…"));
+        state.options.status_timeout = Some(0);
+        state.set_status("kept".to_string());
+        assert!(!state.expire_status(Instant::now() + timeout));
+    }
+
+    #[test]
+    fn pids_of_windows_gone_are_dropped() {
+        let mut window = node(2, vec![]);
+        window.window = Some(7);
+        let (mut state, _) = mock_state(node(1, vec![window]));
+        state.x11_pids.insert(7, Some(100));
+        state.x11_pids.insert(9, Some(200));
+        state.update_tree();
+        assert_eq!(state.x11_pids.keys().collect::<Vec<_>>(), vec![&7]);
+        assert_eq!(state.window_pid(&state.node_tree.nodes[0]), Some(100));
+    }
+
+    #[test]
+    fn cycling_the_layout_reports_it() {
+        let (mut state, commands) = mock_state(node(1, vec![node(2, vec![])]));
+        state.cycle_layout();
+        assert_eq!(
+            state.status.as_deref(),
+            Some("the layout here can't be cycled")
+        );
+        state.select_next();
+        state.cycle_layout();
+        assert_eq!(*commands.borrow(), vec![r#"[con_id="2"] layout splitv"#]);
+        assert_eq!(state.status.as_deref(), Some("layout splitv"));
+    }
+
+    #[test]
+    fn own_window_is_found_again_by_its_x11_window() {
+        let mut terminal = node(3, vec![]);
+        terminal.window = Some(7);
+        let (mut state, _) = mock_state(node(1, vec![node(2, vec![]), terminal]));
+        // As a restarted i3 would have it, the con_id of before is gone
+        state.own_window = Some(30);
+        state.own_window_ids = (Some(7), None);
+        state.update_tree();
+        assert_eq!(state.own_window, Some(3));
+        state.own_window = Some(30);
+        state.own_window_ids = (Some(8), None);
+        state.update_tree();
+        assert_eq!(state.own_window, None);
+    }
+}
@@ -0,0 +1,99 @@
+use std::{
+    env,
+    error::Error,
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::Mutex,
+    time::Instant,
+};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// Appends each record to a file, after the time elapsed since startup
+struct FileLogger {
+    file: Mutex<File>,
+    start: Instant,
+    level: LevelFilter,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let elapsed = self.start.elapsed();
+        if let Ok(mut file) = self.file.lock() {
+            // Nowhere to report a failing log file from the TUI
+            let _ = writeln!(
+                file,
+                "{:>5}.{:03} {:<5} {}",
+                elapsed.as_secs(),
+                elapsed.subsec_millis(),
+                record.level(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// `$RUST_LOG` when it names a level, debug otherwise
+fn level() -> LevelFilter {
+    env::var("RUST_LOG")
+        .ok()
+        .and_then(|level| level.parse().ok())
+        .unwrap_or(LevelFilter::Debug)
+}
+
+/// Log to the end of the file at `path`, nothing is logged without it
+pub(crate) fn init(path: &Path) -> Result<(), Box<dyn Error>> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let level = level();
+    log::set_boxed_logger(Box::new(FileLogger {
+        file: Mutex::new(file),
+        start: Instant::now(),
+        level,
+    }))?;
+    log::set_max_level(level);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Level;
+    use std::fs;
+
+    #[test]
+    fn records_are_appended_below_the_level() {
+        let path = env::temp_dir().join(format!("i3-tree-log-{}.log", std::process::id()));
+        let logger = FileLogger {
+            file: Mutex::new(File::create(&path).unwrap()),
+            start: Instant::now(),
+            level: LevelFilter::Debug,
+        };
+        for (level, message) in [(Level::Debug, "kept"), (Level::Trace, "dropped")].iter() {
+            logger.log(
+                &Record::builder()
+                    .level(*level)
+                    .args(format_args!("{}", message))
+                    .build(),
+            );
+        }
+        logger.flush();
+        let logged = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(logged.trim_end().ends_with("DEBUG kept"));
+        assert_eq!(logged.lines().count(), 1);
+    }
+}
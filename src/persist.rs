@@ -0,0 +1,54 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::NodeId;
+
+/// `$XDG_STATE_HOME/i3_tree_manager/selection`, under `~/.local/state` by default
+fn selection_path() -> PathBuf {
+    env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))
+        .unwrap_or_default()
+        .join("i3_tree_manager")
+        .join("selection")
+}
+
+fn write_selection(path: &Path, id: NodeId) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, format!("{}\n", id))
+}
+
+fn read_selection(path: &Path) -> Option<NodeId> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Remember `id` for the next run
+pub(crate) fn save_selection(id: NodeId) -> io::Result<()> {
+    write_selection(&selection_path(), id)
+}
+
+/// Container selected when the last run quit, if it saved one
+pub(crate) fn load_selection() -> Option<NodeId> {
+    read_selection(&selection_path())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selection_survives_a_round_trip() {
+        let dir = env::temp_dir().join(format!("i3-tree-state-{}", std::process::id()));
+        let path = dir.join("i3_tree_manager").join("selection");
+        assert_eq!(read_selection(&path), None);
+        write_selection(&path, 94_123_456).unwrap();
+        assert_eq!(read_selection(&path), Some(94_123_456));
+        fs::write(&path, "garbage").unwrap();
+        assert_eq!(read_selection(&path), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
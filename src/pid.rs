@@ -0,0 +1,40 @@
+use std::{fs, process::Command};
+
+/// Process owning the X11 window `window`, as `_NET_WM_PID` tells `xprop`
+pub(crate) fn x11_pid(window: i32) -> Option<u32> {
+    let output = Command::new("xprop")
+        .args(["-id", &window.to_string(), "_NET_WM_PID"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_xprop(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// `1234` out of `_NET_WM_PID(CARDINAL) = 1234`, windows without the property
+/// get `_NET_WM_PID:  not found.`
+fn parse_xprop(output: &str) -> Option<u32> {
+    output.split('=').nth(1)?.trim().parse().ok()
+}
+
+/// Command name of the running process `pid`, from `/proc`
+pub(crate) fn process_name(pid: u32) -> Option<String> {
+    let name = fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+    Some(name.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pid_is_read_from_xprop() {
+        assert_eq!(parse_xprop("_NET_WM_PID(CARDINAL) = 1234\n"), Some(1234));
+        assert_eq!(parse_xprop("_NET_WM_PID:  not found.\n"), None);
+        assert_eq!(
+            process_name(std::process::id()).is_some(),
+            cfg!(target_os = "linux")
+        );
+    }
+}
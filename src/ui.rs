@@ -1,20 +1,41 @@
-use std::{error::Error, io, io::Stdout};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    error::Error,
+    fmt::{self, Display},
+    io::{self, Stdout, Write},
+    ops::Range,
+    panic,
+};
 
-use i3ipc::reply::Node;
-use termion::{input::MouseTerminal, raw::IntoRawMode, raw::RawTerminal, screen::AlternateScreen};
+use i3ipc::reply::{Node, NodeBorder, NodeType, WindowProperty, Workspace};
+use termion::{
+    cursor,
+    input::MouseTerminal,
+    raw::IntoRawMode,
+    raw::RawTerminal,
+    screen::{AlternateScreen, ToMainScreen},
+};
 use tui::style::{Color, Modifier, Style};
 use tui::text::{Span, Spans};
-use tui::widgets::Paragraph;
+use tui::widgets::{Paragraph, Widget};
 use tui::{
     backend::TermionBackend,
-    layout::{Constraint, Corner, Direction, Layout},
-    text::Text,
-    widgets::{Block, Borders, List, ListItem},
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    widgets::{Block, Borders, Clear, Row, Table, TableState, Wrap},
     Terminal,
 };
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-use crate::{State, StateMode};
+use crate::{
+    config::{key_name, Action, Options, Theme},
+    find_node,
+    ipc::{NodeExtra, NodeExtras},
+    matches_query, path_to, NodeId, Pending, State, StateMode,
+};
 
+#[allow(dead_code)]
 #[derive(Clone)]
 struct UiNode {
     con_id: i64,
@@ -24,45 +45,261 @@ struct UiNode {
     layout: String,
     focused: bool,
     urgent: bool,
+    fullscreen: bool,
+    marks: Vec<String>,
+    /// `app_id` or `WM_CLASS` of windows, blank for containers
+    class: String,
+    /// Label windows by class rather than by title
+    show_class: bool,
+    /// Tiled and floating children
+    children: usize,
+    /// Levels below the top row
+    depth: usize,
+    /// Follow containers with their children count and depth
+    show_counts: bool,
+    /// Share of the parent, from 0 to 1
+    percent: Option<f64>,
+    floating: bool,
+    /// Holds a window rather than other containers
+    window: bool,
+    /// Output rendered as a header row
+    header: bool,
+    /// Children hidden by folding
+    folded: bool,
+    /// Styled with colors rather than with modifiers only
+    color: bool,
+    /// Focus and urgency fill the background, rather than color the text
+    background: bool,
+    /// Typed to select the row in jump mode
+    jump_label: Option<String>,
+    /// Jump mode is on and the label doesn't start with what was typed
+    jump_missed: bool,
+    /// Width and height
+    size: (i32, i32),
+    /// Left out by the search
+    dimmed: bool,
+    /// Bytes of `name` the search query matches, on matching rows only
+    matched: Vec<Range<usize>>,
+    glyphs: &'static Glyphs,
 }
 
 impl UiNode {
-    fn from(node: Node, indentation: String) -> Self {
+    fn from(node: &Node, view: &TreeView, context: &Context) -> Self {
         Self {
+            glyphs: context.glyphs,
+            floating: context.floating,
+            header: view.output_headers && node.nodetype == NodeType::Output,
+            folded: view.collapsed.contains(&node.id),
+            color: view.color,
+            background: view.background,
+            jump_label: view
+                .jump_labels
+                .get(&node.id)
+                .map(|label| label.to_string()),
+            jump_missed: match (view.jump_typed, view.jump_labels.get(&node.id)) {
+                (Some(typed), Some(label)) => !label.starts_with(typed),
+                (Some(_), None) => true,
+                (None, _) => false,
+            },
+            size: (node.rect.2, node.rect.3),
+            window: node.window.is_some()
+                || (node.nodes.is_empty() && node.floating_nodes.is_empty()),
+            dimmed: view.query.is_some() && !view.relevant.contains(&node.id),
+            matched: match view.query {
+                Some(query) if matches_query(node, query) && !is_scratchpad(node) => {
+                    match_ranges(node.name.as_deref().unwrap_or_default(), query)
+                }
+                _ => vec![],
+            },
+            fullscreen: view
+                .extras
+                .get(&node.id)
+                .is_some_and(|extra| extra.fullscreen_mode > 0),
+            marks: view
+                .extras
+                .get(&node.id)
+                .map(|extra| extra.marks.clone())
+                .unwrap_or_default(),
+            percent: node.percent.filter(|percent| *percent > 0.0),
+            class: window_class(node, view.extras.get(&node.id)).unwrap_or_default(),
+            show_class: view.show_class,
+            children: node.nodes.len() + node.floating_nodes.len(),
+            depth: context.depth,
+            show_counts: view.show_counts,
             con_id: node.id,
-            name: node.name.unwrap_or_default(),
+            name: if is_scratchpad(node) {
+                scratchpad_label(node)
+            } else {
+                node.name.clone().unwrap_or_default()
+            },
             layout: format!("{:?}", node.layout),
             node_type: format!("{:?}", node.nodetype),
             focused: node.focused,
             urgent: node.urgent,
-            indentation,
+            indentation: context.full_entry(),
         }
     }
 }
 
-static BRANCH_INDENT: &str = "│  ";
-static LEAF_INDENT: &str = "   ";
-static BRANCH_GLYPH: &str = "├──";
-static LEAF_GLYPH: &str = "└──";
 static ROOT_GLYPH: &str = "";
 static EMPTY_INDENT: &str = "";
 
-struct Context {
-    ancestors_indent: String,
-    level: TreeLevel,
-    selected_id: Option<i64>,
+/// Characters the tree is drawn with
+struct Glyphs {
+    branch_indent: &'static str,
+    leaf_indent: &'static str,
+    branch: &'static str,
+    leaf: &'static str,
+    floating_branch: &'static str,
+    floating_leaf: &'static str,
+    /// Around output headers
+    rule: &'static str,
+    /// Before the name of folded containers
+    folded: &'static str,
+    bar_full: char,
+    /// Partially filled cells of the percentage bars, from empty up
+    bar_partial: &'static [char],
+    /// Before the name of split-h, split-v, tabbed and stacked containers
+    layouts: [&'static str; 4],
 }
 
-impl Default for Context {
-    fn default() -> Self {
+static UNICODE_GLYPHS: Glyphs = Glyphs {
+    branch_indent: "│  ",
+    leaf_indent: "   ",
+    branch: "├──",
+    leaf: "└──",
+    floating_branch: "├╌╌",
+    floating_leaf: "└╌╌",
+    rule: "━━",
+    folded: "▸ ",
+    bar_full: '█',
+    bar_partial: &[' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'],
+    layouts: ["▭", "▯", "▤", "▥"],
+};
+
+static ASCII_GLYPHS: Glyphs = Glyphs {
+    branch_indent: "|  ",
+    leaf_indent: "   ",
+    branch: "|--",
+    leaf: "`--",
+    floating_branch: "|..",
+    floating_leaf: "`..",
+    rule: "==",
+    folded: "+ ",
+    bar_full: '#',
+    bar_partial: &[' '],
+    layouts: ["H", "V", "T", "S"],
+};
+
+/// Whether the locale announces a UTF-8 terminal, assumed when it is unset
+fn locale_is_utf8() -> bool {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|locale| !locale.is_empty())
+        .is_none_or(|locale| {
+            let locale = locale.to_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        })
+}
+
+fn glyphs(options: &Options) -> &'static Glyphs {
+    if options.ascii.unwrap_or_else(|| !locale_is_utf8()) {
+        &ASCII_GLYPHS
+    } else {
+        &UNICODE_GLYPHS
+    }
+}
+
+/// Colors are left out when `NO_COLOR` is set to anything, see https://no-color.org
+fn color_enabled() -> bool {
+    env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+/// State wide inputs to the tree rendering
+struct TreeView<'a> {
+    extras: &'a NodeExtras,
+    /// Search query, underlined in the names of matching rows
+    query: Option<&'a str>,
+    /// Search matches and their ancestors
+    relevant: HashSet<NodeId>,
+    output_headers: bool,
+    show_class: bool,
+    show_counts: bool,
+    collapsed: &'a HashSet<NodeId>,
+    color: bool,
+    /// Row labels of jump mode, by con_id
+    jump_labels: HashMap<NodeId, &'a str>,
+    /// What was typed of a label, in jump mode
+    jump_typed: Option<&'a str>,
+    theme: &'a Theme,
+    glyphs: &'static Glyphs,
+    /// Focus and urgency fill the background, rather than color the text
+    background: bool,
+}
+
+impl<'a> TreeView<'a> {
+    /// Inputs of the default config, outside of any search
+    fn plain(extras: &'a NodeExtras, collapsed: &'a HashSet<NodeId>, theme: &'a Theme) -> Self {
         Self {
-            ancestors_indent: EMPTY_INDENT.to_string(),
-            level: TreeLevel::Root,
-            selected_id: None,
+            extras,
+            query: None,
+            relevant: HashSet::new(),
+            output_headers: Options::default().output_headers,
+            show_class: false,
+            show_counts: false,
+            collapsed,
+            color: true,
+            jump_labels: HashMap::new(),
+            jump_typed: None,
+            theme,
+            glyphs: &UNICODE_GLYPHS,
+            background: true,
         }
     }
 }
 
+impl<'a> From<&'a State> for TreeView<'a> {
+    fn from(state: &'a State) -> Self {
+        Self {
+            extras: &state.node_extras,
+            query: state.search_query(),
+            relevant: state.search_relevant(),
+            output_headers: state.options.output_headers,
+            show_class: state.show_class,
+            show_counts: state.show_counts,
+            collapsed: &state.collapsed,
+            color: color_enabled(),
+            jump_labels: match state.mode {
+                StateMode::Jump(ref labels, _) => labels
+                    .iter()
+                    .map(|(label, node_id)| (*node_id, label.as_str()))
+                    .collect(),
+                _ => HashMap::new(),
+            },
+            jump_typed: match state.mode {
+                StateMode::Jump(_, ref typed) => Some(typed.text()),
+                _ => None,
+            },
+            theme: &state.theme,
+            glyphs: glyphs(&state.options),
+            background: !state.no_background,
+        }
+    }
+}
+
+struct Context {
+    ancestors_indent: String,
+    level: TreeLevel,
+    /// Whether the node lives in a floating container
+    floating: bool,
+    /// Whether the node is one of the `floating_nodes` of its parent
+    detached: bool,
+    /// Levels below the top row
+    depth: usize,
+    glyphs: &'static Glyphs,
+}
+
 enum TreeLevel {
     Root,
     Branch,
@@ -70,20 +307,34 @@ enum TreeLevel {
 }
 
 impl Context {
+    /// Context of the root of the tree
+    fn new(glyphs: &'static Glyphs) -> Self {
+        Self {
+            ancestors_indent: EMPTY_INDENT.to_string(),
+            level: TreeLevel::Root,
+            floating: false,
+            detached: false,
+            depth: 0,
+            glyphs,
+        }
+    }
+
     fn descendant_indent(&self) -> String {
         let fill = match self.level {
             TreeLevel::Root => EMPTY_INDENT,
-            TreeLevel::Branch => BRANCH_INDENT,
-            TreeLevel::Leaf => LEAF_INDENT,
+            TreeLevel::Branch => self.glyphs.branch_indent,
+            TreeLevel::Leaf => self.glyphs.leaf_indent,
         };
         format!("{}{}", self.ancestors_indent, fill)
     }
 
     fn glyph(&self) -> String {
-        match self.level {
-            TreeLevel::Root => ROOT_GLYPH,
-            TreeLevel::Branch => BRANCH_GLYPH,
-            TreeLevel::Leaf => LEAF_GLYPH,
+        match (&self.level, self.detached) {
+            (TreeLevel::Root, _) => ROOT_GLYPH,
+            (TreeLevel::Branch, false) => self.glyphs.branch,
+            (TreeLevel::Branch, true) => self.glyphs.floating_branch,
+            (TreeLevel::Leaf, false) => self.glyphs.leaf,
+            (TreeLevel::Leaf, true) => self.glyphs.floating_leaf,
         }
         .to_string()
     }
@@ -96,7 +347,10 @@ impl Context {
         Self {
             ancestors_indent: self.descendant_indent(),
             level: TreeLevel::Leaf,
-            selected_id: self.selected_id,
+            floating: self.floating,
+            detached: false,
+            depth: self.depth + 1,
+            glyphs: self.glyphs,
         }
     }
 
@@ -104,56 +358,474 @@ impl Context {
         Self {
             ancestors_indent: self.descendant_indent(),
             level: TreeLevel::Branch,
-            selected_id: self.selected_id,
+            floating: self.floating,
+            detached: false,
+            depth: self.depth + 1,
+            glyphs: self.glyphs,
+        }
+    }
+}
+
+/// Recursively flatten the tree into its rows
+fn node_into_ui_nodes(node: &Node, view: &TreeView, context: Context) -> Vec<UiNode> {
+    let mut tree_list = Vec::new();
+    push_ui_nodes(node, view, context, &mut tree_list);
+    tree_list
+}
+
+fn push_ui_nodes(node: &Node, view: &TreeView, context: Context, tree_list: &mut Vec<UiNode>) {
+    // Floating windows of the compact view sit right in `floating_nodes`
+    let context = Context {
+        floating: context.floating || context.detached || node.nodetype == NodeType::FloatingCon,
+        ..context
+    };
+    tree_list.push(UiNode::from(node, view, &context));
+    if view.collapsed.contains(&node.id) {
+        return;
+    }
+    // Floating children follow the tiled ones, as in `collect_ids`
+    let branches: Vec<(&Node, bool)> = node
+        .nodes
+        .iter()
+        .map(|child| (child, false))
+        .chain(node.floating_nodes.iter().map(|child| (child, true)))
+        .collect();
+
+    if let Some(((last, last_detached), branches)) = branches.split_last() {
+        for (branch, detached) in branches {
+            let context = Context {
+                detached: *detached,
+                ..context.to_branch()
+            };
+            push_ui_nodes(branch, view, context, tree_list);
         }
+        let context = Context {
+            detached: *last_detached,
+            ..context.to_leaf()
+        };
+        push_ui_nodes(last, view, context, tree_list);
     }
 }
 
-/// Recursively build a list of items with string representation of tree
-fn node_into_ui_list<'a>(node: &Node, context: Context) -> Vec<ListItem<'a>> {
-    let mut root = ListItem::new(UiNode::from(node.clone(), context.full_entry()));
-    if node.urgent {
-        root = root.style(Style::default().bg(Color::LightMagenta));
+/// Base style of each kind of node
+fn node_type_style(ui_node: &UiNode, theme: &Theme) -> Style {
+    match ui_node.node_type.as_str() {
+        "Root" => Style::default().add_modifier(Modifier::BOLD),
+        "Output" => Style::default()
+            .fg(theme.output)
+            .add_modifier(Modifier::BOLD),
+        "Workspace" => Style::default().fg(theme.workspace),
+        "Con" | "FloatingCon" if ui_node.window => Style::default()
+            .fg(theme.window)
+            .add_modifier(Modifier::BOLD),
+        "Con" | "FloatingCon" => Style::default().add_modifier(Modifier::DIM),
+        _ => Style::default(),
+    }
+}
+
+/// Highlights are layered over the node type style, later ones win
+fn ui_node_style(ui_node: &UiNode, theme: &Theme) -> Style {
+    let mut style = if ui_node.header {
+        Style::default()
+            .fg(theme.output)
+            .add_modifier(Modifier::BOLD | Modifier::REVERSED)
+    } else {
+        node_type_style(ui_node, theme)
+    };
+    if ui_node.floating {
+        style = style.fg(theme.floating);
+    }
+    if ui_node.dimmed || ui_node.jump_missed {
+        style = style.add_modifier(Modifier::DIM);
+    }
+    if ui_node.urgent && ui_node.background {
+        style = style.bg(theme.urgent);
+    } else if ui_node.urgent {
+        style = style.fg(theme.urgent).add_modifier(Modifier::BOLD);
+    }
+    if ui_node.focused && ui_node.background {
+        style = style.bg(theme.focused);
+    } else if ui_node.focused {
+        style = style
+            .fg(theme.focused)
+            .add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+    }
+    if !ui_node.color {
+        style = Style {
+            fg: None,
+            bg: None,
+            ..style
+        };
+        // The selection is already reversed, focus gets another modifier
+        if ui_node.urgent {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        if ui_node.focused {
+            style = style.add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+        }
+    }
+    style
+}
+
+/// Headers of the tree table
+static TREE_COLUMNS: &[&str] = &["Name", "Type", "Layout", "Share"];
+/// Columns after the name, `FloatingCon`, `DockArea` and `[██████] 100%` long
+const TYPE_COLUMN_WIDTH: u16 = 11;
+const LAYOUT_COLUMN_WIDTH: u16 = 8;
+const SHARE_COLUMN_WIDTH: u16 = 13;
+
+/// Cells of a row of the tree table, along with its style and the columns
+/// of the name cell to underline
+pub(crate) type TreeRow = ([String; 4], Style, Vec<Range<u16>>);
+
+/// Width left to the name column out of the `width` of the table
+fn name_column_width(width: u16) -> u16 {
+    let spacing = TREE_COLUMNS.len() as u16 - 1;
+    width.saturating_sub(TYPE_COLUMN_WIDTH + LAYOUT_COLUMN_WIDTH + SHARE_COLUMN_WIDTH + spacing)
+}
+
+/// Styled cells of each row of the tree, the names fitting in `name_width`
+fn node_into_ui_rows(
+    node: &Node,
+    view: &TreeView,
+    context: Context,
+    name_width: usize,
+) -> Vec<TreeRow> {
+    node_into_ui_nodes(node, view, context)
+        .into_iter()
+        .map(|ui_node| {
+            let style = ui_node_style(&ui_node, view.theme);
+            let matched = ui_node.matched_columns(name_width);
+            (ui_node.cells(name_width), style, matched)
+        })
+        .collect()
+}
+
+/// Rows of `tree` with the default config, outside of any search
+pub(crate) fn plain_rows(tree: &Node, name_width: usize) -> Vec<TreeRow> {
+    let extras = NodeExtras::new();
+    let collapsed = HashSet::new();
+    let theme = Theme::default();
+    let view = TreeView::plain(&extras, &collapsed, &theme);
+    node_into_ui_rows(tree, &view, Context::new(view.glyphs), name_width)
+}
+
+/// `text` cut to `width` terminal columns, ending with `…` when cut
+fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
     }
-    if node.focused {
-        root = root.style(Style::default().bg(Color::LightGreen));
+    let mut truncated = String::new();
+    let mut used = 0;
+    // Keep a column for the ellipsis, wide characters never straddle the cut
+    for c in text.chars() {
+        let c_width = c.width().unwrap_or_default();
+        if used + c_width + 1 > width {
+            break;
+        }
+        used += c_width;
+        truncated.push(c);
     }
-    if Some(node.id) == context.selected_id {
-        root = root.style(Style::default().add_modifier(Modifier::REVERSED));
+    if width > 0 {
+        truncated.push('…');
     }
+    truncated
+}
 
-    let mut tree_list = vec![root];
-    let mut branches = node.nodes.clone();
-    let leaf = branches.pop();
+/// Workspace holding the windows sent to the scratchpad
+fn is_scratchpad(node: &Node) -> bool {
+    node.nodetype == NodeType::Workspace && node.name.as_deref() == Some("__i3_scratch")
+}
 
-    if let Some(ref last) = leaf {
-        branches
+fn scratchpad_label(node: &Node) -> String {
+    match node.nodes.len() + node.floating_nodes.len() {
+        0 => "Scratchpad (empty)".to_string(),
+        1 => "Scratchpad (1 hidden window)".to_string(),
+        hidden => format!("Scratchpad ({} hidden windows)", hidden),
+    }
+}
+
+/// Wayland `app_id`, or X11 `WM_CLASS`
+fn window_class(node: &Node, extra: Option<&NodeExtra>) -> Option<String> {
+    extra
+        .and_then(|extra| extra.app_id.clone())
+        .or_else(|| {
+            node.window_properties
+                .as_ref()
+                .and_then(|properties| properties.get(&WindowProperty::Class))
+                .cloned()
+        })
+        .filter(|class| !class.is_empty())
+}
+
+impl UiNode {
+    /// Class or title shown for the node
+    fn label(&self) -> &str {
+        if self.show_class && !self.class.is_empty() {
+            &self.class
+        } else {
+            &self.name
+        }
+    }
+
+    /// Whatever precedes the name
+    fn prefix(&self) -> String {
+        if self.header {
+            return self.lead();
+        }
+        let mut prefix = format!(
+            "{}{}[{}] {{{}}}",
+            self.indentation,
+            self.fold_marker(),
+            self.node_type,
+            self.layout
+        );
+        if let Some(percent) = self.percent {
+            prefix.push(' ');
+            prefix.push_str(&percent_bar(percent, self.glyphs));
+        }
+        prefix.push_str(" - ");
+        prefix
+    }
+
+    /// Whatever follows the name
+    fn suffix(&self) -> String {
+        if self.header {
+            let (width, height) = self.size;
+            return format!(" {}x{} {}", width, height, self.glyphs.rule);
+        }
+        let mut suffix: String = self
+            .marks
             .iter()
-            .fold(&mut tree_list, |lst, node| {
-                lst.append(&mut node_into_ui_list(node, context.to_branch()));
-                lst
-            })
-            .append(&mut node_into_ui_list(last, context.to_leaf()))
+            .map(|mark| format!(" [{}]", mark))
+            .collect();
+        if self.fullscreen {
+            suffix.push_str(" [fullscreen]");
+        }
+        if self.show_counts && !self.window {
+            let plural = if self.children == 1 {
+                "child"
+            } else {
+                "children"
+            };
+            suffix.push_str(&format!(
+                " ({} {}, depth {})",
+                self.children, plural, self.depth
+            ));
+        }
+        suffix
+    }
+
+    /// Glyph of the layout of containers, windows have no use for theirs
+    fn layout_icon(&self) -> Option<&'static str> {
+        if self.window {
+            return None;
+        }
+        let index = match self.layout.as_str() {
+            "SplitH" => 0,
+            "SplitV" => 1,
+            "Tabbed" => 2,
+            "Stacked" => 3,
+            _ => return None,
+        };
+        Some(self.glyphs.layouts[index])
+    }
+
+    fn fold_marker(&self) -> &'static str {
+        if self.folded {
+            self.glyphs.folded
+        } else {
+            ""
+        }
+    }
+
+    /// Indentation and what precedes the name in the name column
+    fn lead(&self) -> String {
+        let lead = if self.header {
+            format!(
+                "{}{}{} ",
+                self.indentation,
+                self.fold_marker(),
+                self.glyphs.rule
+            )
+        } else {
+            let icon = self
+                .layout_icon()
+                .map(|icon| format!("{} ", icon))
+                .unwrap_or_default();
+            format!("{}{}{}", self.indentation, self.fold_marker(), icon)
+        };
+        match self.jump_label {
+            Some(ref label) => format!("{:<2} {}", label, lead),
+            None => lead,
+        }
+    }
+
+    /// Columns of the name cell, shortened to `width`, where the name shows
+    /// what the search query matched. The label is the name unless a class
+    /// stands for it
+    fn matched_columns(&self, width: usize) -> Vec<Range<u16>> {
+        if self.label() != self.name {
+            return vec![];
+        }
+        let lead = self.lead().width();
+        let name_width = match width.checked_sub(lead + self.suffix().width()) {
+            Some(name_width) if name_width > 0 => name_width,
+            _ => return vec![],
+        };
+        // Bytes of the name left of the ellipsis, when it is cut
+        let shown = truncate(&self.name, name_width);
+        let kept = if shown == self.name {
+            self.name.len()
+        } else {
+            shown.len() - '…'.len_utf8()
+        };
+        let column = |byte: usize| (lead + self.name[..byte].width()) as u16;
+        self.matched
+            .iter()
+            .filter(|range| range.start < kept)
+            .map(|range| column(range.start)..column(range.end.min(kept)))
+            .collect()
+    }
+
+    /// Name column shortened to `width` by cutting the name first
+    fn name_cell(&self, width: usize) -> String {
+        let (prefix, suffix) = (self.lead(), self.suffix());
+        let fixed = prefix.width() + suffix.width();
+        match width.checked_sub(fixed) {
+            Some(name_width) if name_width > 0 || self.label().is_empty() => {
+                format!("{}{}{}", prefix, truncate(self.label(), name_width), suffix)
+            }
+            _ => truncate(&format!("{}{}{}", prefix, self.label(), suffix), width),
+        }
+    }
+
+    fn cells(&self, name_width: usize) -> [String; 4] {
+        if self.header {
+            return [
+                self.name_cell(name_width),
+                String::new(),
+                String::new(),
+                String::new(),
+            ];
+        }
+        [
+            self.name_cell(name_width),
+            self.node_type.clone(),
+            self.layout.clone(),
+            self.percent
+                .map(|percent| percent_bar(percent, self.glyphs))
+                .unwrap_or_default(),
+        ]
     }
-    tree_list
 }
 
-impl From<UiNode> for Text<'_> {
-    fn from(ui_node: UiNode) -> Self {
-        Self::from(format!(
-            "{}[{}] {{{}}} - {}",
-            ui_node.indentation, ui_node.node_type, ui_node.layout, ui_node.name
-        ))
+impl fmt::Display for UiNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}{}", self.prefix(), self.label(), self.suffix())
+    }
+}
+
+/// Cells of the percentage bars
+const PERCENT_BAR_WIDTH: usize = 6;
+
+/// `[███▌  ] 62%` for 0.62
+fn percent_bar(percent: f64, glyphs: &Glyphs) -> String {
+    let percent = percent.clamp(0.0, 1.0);
+    // Each cell fills up in as many steps as there are partial glyphs
+    let steps = glyphs.bar_partial.len();
+    let filled = (percent * (PERCENT_BAR_WIDTH * steps) as f64).round() as usize;
+    let mut bar: String = std::iter::repeat_n(glyphs.bar_full, filled / steps).collect();
+    let partial = filled % steps;
+    if partial > 0 {
+        bar.push(glyphs.bar_partial[partial]);
     }
+    let padding = PERCENT_BAR_WIDTH - bar.chars().count();
+    format!("[{}{}] {:.0}%", bar, " ".repeat(padding), percent * 100.0)
 }
 
-fn build_tree_widget(tree_items: Vec<ListItem>) -> List {
-    List::new(tree_items)
+/// Write the tree as it is listed in the TUI, without styling
+pub(crate) fn print_tree(state: &State, out: &mut impl Write) -> io::Result<()> {
+    let view = TreeView::from(state);
+    for ui_node in node_into_ui_nodes(state.view_root(), &view, Context::new(view.glyphs)) {
+        writeln!(out, "{}", ui_node)?;
+    }
+    Ok(())
+}
+
+/// The selected row keeps its own style, reversed unless it gets `selected_color`
+fn build_tree_widget<'a>(
+    rows: &'a [TreeRow],
+    widths: &'a [Constraint],
+    selected: Option<usize>,
+    selected_color: Option<Color>,
+) -> Table<'a, std::slice::Iter<'a, &'a str>, impl Iterator<Item = Row<std::slice::Iter<'a, String>>>>
+{
+    let style = selected
+        .and_then(|index| rows.get(index))
+        .map_or_else(Style::default, |(_, style, _)| *style);
+    let highlight_style = match selected_color {
+        Some(color) => style.bg(color),
+        None => style.add_modifier(Modifier::REVERSED),
+    };
+    let rows = rows
+        .iter()
+        .map(|(cells, style, _)| Row::StyledData(cells.iter(), *style));
+    Table::new(TREE_COLUMNS.iter(), rows)
         .block(Block::default().borders(Borders::ALL).title("I3 Tree"))
-        .start_corner(Corner::TopLeft)
+        .header_style(Style::default().add_modifier(Modifier::BOLD))
+        .header_gap(0)
+        .widths(widths)
+        .highlight_style(highlight_style)
 }
 
-fn build_menu_span<'a>(mode: &'a str, actions: Vec<(&'a str, &'a str)>) -> Spans<'a> {
+/// Bytes of `text` a case insensitive `query` matches. Lowercase letters may
+/// be longer than the original ones, so matches are found in the lowercase
+/// text and mapped back to the characters they cover
+fn match_ranges(text: &str, query: &str) -> Vec<Range<usize>> {
+    let query = query.to_lowercase();
+    if query.is_empty() {
+        return vec![];
+    }
+    let mut lower = String::new();
+    // Byte of `text` each byte of `lower` comes from
+    let mut origin = vec![];
+    for (offset, c) in text.char_indices() {
+        for lower_c in c.to_lowercase() {
+            lower.push(lower_c);
+            origin.resize(lower.len(), offset);
+        }
+    }
+    let end_of = |offset: usize| offset + text[offset..].chars().next().map_or(0, char::len_utf8);
+    lower
+        .match_indices(&query)
+        .map(|(start, matched)| origin[start]..end_of(origin[start + matched.len() - 1]))
+        .collect()
+}
+
+/// Underlines the columns of the name cells `matched_columns` found, on the
+/// rows drawn in its area from the `offset`th on. Table cells of tui are
+/// plain strings, so the table is drawn first and the columns styled after
+struct MatchHighlight<'a> {
+    rows: &'a [TreeRow],
+    offset: usize,
+}
+
+impl Widget for MatchHighlight<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let style = Style::default().add_modifier(Modifier::UNDERLINED | Modifier::BOLD);
+        let rows = self.rows.iter().skip(self.offset);
+        for (y, (_, _, matched)) in (area.top()..area.bottom()).zip(rows) {
+            for x in matched.iter().flat_map(Range::clone) {
+                if x < area.width {
+                    buf.get_mut(area.left() + x, y).set_style(style);
+                }
+            }
+        }
+    }
+}
+
+fn build_menu_span<'a, K: Display>(mode: String, actions: Vec<(K, &'a str)>) -> Spans<'a> {
     let mode = Span::styled(
         format!("{} ┃", mode),
         Style::default().add_modifier(Modifier::REVERSED),
@@ -175,65 +847,1344 @@ fn build_menu_span<'a>(mode: &'a str, actions: Vec<(&'a str, &'a str)>) -> Spans
     Spans::from(actions)
 }
 
-fn build_menu_widget(state: &State) -> Paragraph {
+static MOVE_ACTIONS: &[(&str, &str)] = &[
+    ("ESC", "exit mode"),
+    ("UP", "move up"),
+    ("DOWN", "move down"),
+    ("LEFT", "move left"),
+    ("RIGHT", "move right"),
+    ("c", "center a floating window"),
+];
+
+static RESIZE_ACTIONS: &[(&str, &str)] = &[
+    ("ESC", "exit mode"),
+    ("UP", "shrink height"),
+    ("DOWN", "grow height"),
+    ("LEFT", "shrink width"),
+    ("RIGHT", "grow width"),
+];
+
+static LAYOUT_ACTIONS: &[(&str, &str)] = &[
+    ("ESC", "exit mode"),
+    ("e", "equalize siblings"),
+    ("h", "layout split-h"),
+    ("v", "layout split-v"),
+    ("t", "layout tabbed"),
+    ("s", "layout stacking"),
+];
+
+static GAPS_ACTIONS: &[(&str, &str)] = &[
+    ("ESC", "exit mode"),
+    ("UP", "grow inner gaps"),
+    ("DOWN", "shrink inner gaps"),
+    ("LEFT", "shrink outer gaps"),
+    ("RIGHT", "grow outer gaps"),
+];
+
+static SEARCH_ACTIONS: &[(&str, &str)] = &[
+    ("ENTER", "commit"),
+    ("LEFT/RIGHT", "move the cursor"),
+    ("ESC", "cancel"),
+];
+
+static COMMAND_ACTIONS: &[(&str, &str)] = &[
+    ("ENTER", "run"),
+    ("LEFT/RIGHT", "move the cursor"),
+    ("ESC", "cancel"),
+];
+
+static MARK_ACTIONS: &[(&str, &str)] = &[
+    ("ENTER", "set marks"),
+    ("LEFT/RIGHT", "move the cursor"),
+    ("ESC", "cancel"),
+];
+
+static MOVE_TO_WORKSPACE_ACTIONS: &[(&str, &str)] = &[
+    ("0-9", "workspace number"),
+    ("ENTER", "move"),
+    ("LEFT/RIGHT", "move the cursor"),
+    ("ESC", "cancel"),
+];
+
+static JUMP_ACTIONS: &[(&str, &str)] = &[
+    ("a-z", "type the label of a row"),
+    ("BACKSPACE", "erase a letter"),
+    ("ESC", "cancel"),
+];
+
+static WORKSPACES_ACTIONS: &[(&str, &str)] = &[("ENTER", "switch to workspace"), ("ESC", "close")];
+
+static MARKS_ACTIONS: &[(&str, &str)] = &[("ENTER", "select container"), ("ESC", "close")];
+
+static RAW_JSON_ACTIONS: &[(&str, &str)] = &[
+    ("PAGEUP", "scroll up a page"),
+    ("PAGEDOWN", "scroll down a page"),
+    ("ESC", "close"),
+];
+
+static CONFIRM_ACTIONS: &[(&str, &str)] = &[("y", "confirm"), ("any", "cancel")];
+
+/// What the pending command is about to do
+fn confirm_question(state: &State, pending: &Pending) -> String {
+    match pending {
+        Pending::Kill(node_id) => {
+            let pid = find_node(&state.node_tree, *node_id)
+                .and_then(|node| state.window_pid(node))
+                .map(|pid| format!(" (pid {})", pid))
+                .unwrap_or_default();
+            format!("Kill {}{}?", node_label(state, *node_id), pid)
+        }
+        Pending::MoveToScratchpad(node_id) => {
+            format!("Hide {} in the scratchpad?", node_label(state, *node_id))
+        }
+        Pending::Command(command) => format!("Run {}?", command),
+    }
+}
+
+fn build_confirm_widget(question: String) -> Paragraph<'static> {
+    let lines = vec![
+        Spans::from(Span::styled(
+            question,
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Spans::default(),
+        Spans::from("y to confirm, any other key to cancel"),
+    ];
+    Paragraph::new(lines)
+        .block(Block::default().title("Confirm").borders(Borders::ALL))
+        .wrap(Wrap { trim: false })
+}
+
+/// Name of the node, or its con_id for unnamed containers
+fn node_label(state: &State, node_id: NodeId) -> String {
+    find_node(&state.node_tree, node_id)
+        .and_then(|node| node.name.clone())
+        .unwrap_or_else(|| node_id.to_string())
+}
+
+/// `output > workspace > container > window` down to `node_id`, the root left out
+fn breadcrumb(tree: &Node, node_id: NodeId) -> String {
+    path_to(tree, node_id)
+        .unwrap_or_default()
+        .into_iter()
+        .skip(1)
+        .filter_map(|id| find_node(tree, id))
+        .map(|node| match node.name {
+            Some(ref name) => name.clone(),
+            None => format!("{:?} {{{:?}}}", node.nodetype, node.layout),
+        })
+        .collect::<Vec<_>>()
+        .join(" > ")
+}
+
+/// Border style as the `border` command takes it
+fn border_label(node: &Node) -> String {
+    match node.border {
+        NodeBorder::Normal => "normal".to_string(),
+        NodeBorder::Pixel => format!("pixel {}", node.current_border_width),
+        NodeBorder::None => "none".to_string(),
+        NodeBorder::Unknown => "unknown".to_string(),
+    }
+}
+
+/// `1234 (firefox)`, blank without a process
+fn pid_label(pid: Option<u32>) -> String {
+    match pid {
+        Some(pid) => match crate::pid::process_name(pid) {
+            Some(name) => format!("{} ({})", pid, name),
+            None => pid.to_string(),
+        },
+        None => String::new(),
+    }
+}
+
+/// `label: value` lines describing the node, `floating` when it lives in a
+/// floating container
+fn details_lines(
+    node: &Node,
+    extra: Option<&NodeExtra>,
+    floating: bool,
+    pid: Option<u32>,
+) -> Vec<String> {
+    let property = |property| {
+        node.window_properties
+            .as_ref()
+            .and_then(|properties| properties.get(&property))
+            .cloned()
+            .unwrap_or_default()
+    };
+    let (x, y, width, height) = node.rect;
+    let marks = extra
+        .map(|extra| extra.marks.join(", "))
+        .unwrap_or_default();
+    vec![
+        format!("con_id: {}", node.id),
+        format!("type: {:?}", node.nodetype),
+        format!("layout: {:?}", node.layout),
+        format!("border: {}", border_label(node)),
+        format!("title: {}", node.name.as_deref().unwrap_or_default()),
+        format!("class: {}", property(WindowProperty::Class)),
+        format!("instance: {}", property(WindowProperty::Instance)),
+        format!(
+            "app_id: {}",
+            extra
+                .and_then(|extra| extra.app_id.as_deref())
+                .unwrap_or_default()
+        ),
+        format!("marks: {}", marks),
+        format!("pid: {}", pid_label(pid)),
+        format!("rect: {}x{} at {},{}", width, height, x, y),
+        format!(
+            "percent: {}",
+            node.percent
+                .map(|percent| format!("{:.1}%", percent * 100.0))
+                .unwrap_or_default()
+        ),
+        format!("floating: {}", floating),
+        format!(
+            "fullscreen mode: {}",
+            extra.map_or(0, |extra| extra.fullscreen_mode)
+        ),
+        format!("urgent: {}", node.urgent),
+        format!("focused: {}", node.focused),
+    ]
+}
+
+fn build_details_widget(state: &State) -> Paragraph<'_> {
+    let floating = state.is_floating(state.selected);
+    let lines = find_node(&state.node_tree, state.selected)
+        .map(|node| {
+            let pid = state.window_pid(node);
+            details_lines(node, state.node_extras.get(&node.id), floating, pid)
+        })
+        .unwrap_or_default();
+    let lines: Vec<Spans> = lines.into_iter().map(Spans::from).collect();
+    Paragraph::new(lines).block(Block::default().title("Details").borders(Borders::ALL))
+}
+
+fn build_raw_json_widget(state: &State, scroll: u16) -> Paragraph<'_> {
+    Paragraph::new(state.raw_json())
+        .scroll((scroll, 0))
+        .block(Block::default().title("JSON").borders(Borders::ALL))
+}
+
+/// Lines inside the borders of the side pane, for the terminal size right now
+pub(crate) fn side_pane_height() -> u16 {
+    let (width, height) = termion::terminal_size().unwrap_or((80, 24));
+    Panes::new(Rect::new(0, 0, width, height), true)
+        .side
+        .map_or(1, |side| side.height.saturating_sub(2).max(1))
+}
+
+/// `*` focused, `+` visible and `!` urgent flags, then the name and output
+fn workspace_line(workspace: &Workspace) -> String {
+    let flag = |set: bool, flag: char| if set { flag } else { ' ' };
+    format!(
+        "{}{}{} {}  {}",
+        flag(workspace.focused, '*'),
+        flag(workspace.visible, '+'),
+        flag(workspace.urgent, '!'),
+        workspace.name,
+        workspace.output
+    )
+}
+
+fn build_workspaces_widget(state: &State, chosen: usize) -> Paragraph<'_> {
+    let lines = state.workspaces.iter().map(workspace_line).collect();
+    Paragraph::new(chosen_lines(lines, chosen))
+        .block(Block::default().title("Workspaces").borders(Borders::ALL))
+}
+
+/// Pane rows, `chosen` reversed
+fn chosen_lines(lines: Vec<String>, chosen: usize) -> Vec<Spans<'static>> {
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(index, line)| {
+            let style = if index == chosen {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            Spans::from(Span::styled(line, style))
+        })
+        .collect()
+}
+
+/// Each mark along with the container it is set on
+fn build_marks_widget(state: &State, chosen: usize) -> Paragraph<'_> {
+    let lines = state
+        .marks
+        .iter()
+        .map(|mark| match state.marked_id(mark) {
+            Some(id) => format!("{}  {}", mark, node_label(state, id)),
+            None => mark.clone(),
+        })
+        .collect();
+    Paragraph::new(chosen_lines(lines, chosen))
+        .block(Block::default().title("Marks").borders(Borders::ALL))
+}
+
+fn build_breadcrumb_widget(state: &State) -> Paragraph<'_> {
+    Paragraph::new(breadcrumb(&state.node_tree, state.selected))
+        .style(Style::default().add_modifier(Modifier::BOLD))
+}
+
+fn build_menu_widget(state: &State) -> Paragraph<'_> {
     let block = Block::default().title("Commands").borders(Borders::ALL);
 
     let menu_span = match state.mode {
-        StateMode::Move(_) => {
+        StateMode::Move(node_id) => build_menu_span(
+            format!("Move {}", node_label(state, node_id)),
+            MOVE_ACTIONS.to_vec(),
+        ),
+        StateMode::Swap(node_id) => {
+            let swap_key = state.keymap.label(Action::Swap);
+            build_menu_span(
+                format!("Swap {} with", node_label(state, node_id)),
+                vec![
+                    ("ESC".to_string(), "cancel"),
+                    (swap_key, "swap with selection"),
+                ],
+            )
+        }
+        StateMode::Resize(node_id) => build_menu_span(
+            format!("Resize {}", node_label(state, node_id)),
+            RESIZE_ACTIONS.to_vec(),
+        ),
+        StateMode::Layout => {
+            let layout = state
+                .layout_target()
+                .map(|node| format!(" {{{:?}}}", node.layout))
+                .unwrap_or_default();
+            build_menu_span(format!("Layout{}", layout), LAYOUT_ACTIONS.to_vec())
+        }
+        StateMode::Gaps => {
+            let gaps = state
+                .current_gaps()
+                .map(|(inner, outer)| format!(" inner {} outer {}", inner, outer))
+                .unwrap_or_default();
+            build_menu_span(format!("Gaps{}", gaps), GAPS_ACTIONS.to_vec())
+        }
+        StateMode::Search(ref query) => {
+            build_menu_span(format!("Search /{}", query.text()), SEARCH_ACTIONS.to_vec())
+        }
+        StateMode::Command(_) => build_menu_span("Command".to_string(), COMMAND_ACTIONS.to_vec()),
+        StateMode::Mark(_) => build_menu_span(
+            format!("Mark {}", node_label(state, state.selected)),
+            MARK_ACTIONS.to_vec(),
+        ),
+        StateMode::MoveToWorkspace(node_id, ref number) => build_menu_span(
+            format!(
+                "Move {} to workspace {}",
+                node_label(state, node_id),
+                number.text()
+            ),
+            MOVE_TO_WORKSPACE_ACTIONS.to_vec(),
+        ),
+        StateMode::Jump(_, ref typed) => {
+            build_menu_span(format!("Jump {}", typed.text()), JUMP_ACTIONS.to_vec())
+        }
+        StateMode::Confirm(ref pending) => {
+            build_menu_span(confirm_question(state, pending), CONFIRM_ACTIONS.to_vec())
+        }
+        StateMode::Workspaces(_) => build_menu_span(
+            "Workspaces".to_string(),
+            vec![
+                (state.keymap.label(Action::SelectNext), "next"),
+                (state.keymap.label(Action::SelectPrevious), "previous"),
+                ("ENTER".to_string(), "switch"),
+                ("ESC".to_string(), "close"),
+            ],
+        ),
+        StateMode::Marks(_) => build_menu_span(
+            "Marks".to_string(),
+            vec![
+                (state.keymap.label(Action::SelectNext), "next"),
+                (state.keymap.label(Action::SelectPrevious), "previous"),
+                ("ENTER".to_string(), "select"),
+                ("ESC".to_string(), "close"),
+            ],
+        ),
+        StateMode::RawJson(_) => build_menu_span(
+            "JSON".to_string(),
+            vec![
+                (state.keymap.label(Action::SelectNext), "next"),
+                (state.keymap.label(Action::SelectPrevious), "previous"),
+                ("PAGEUP/PAGEDOWN".to_string(), "scroll"),
+                ("ESC".to_string(), "close"),
+            ],
+        ),
+        StateMode::Help => build_menu_span("Help".to_string(), vec![("any", "close")]),
+        StateMode::None => {
+            let keys = |actions: &[Action]| {
+                actions
+                    .iter()
+                    .map(|action| state.keymap.label(*action))
+                    .collect::<Vec<_>>()
+                    .join("/")
+            };
+            // The core of it, `?` lists every action
             let actions = vec![
-                ("ESC", "exit mode"),
-                ("UP", "move up"),
-                ("DOWN", "move down"),
-                ("LEFT", "move left"),
-                ("RIGHT", "move right"),
+                (
+                    keys(&[Action::SelectNext, Action::SelectPrevious]),
+                    "select",
+                ),
+                (
+                    keys(&[Action::SelectParent, Action::SelectFirstChild]),
+                    "parent/child",
+                ),
+                (keys(&[Action::Focus]), "focus"),
+                (keys(&[Action::Search]), "search"),
+                (keys(&[Action::MoveMode]), "move"),
+                (keys(&[Action::ResizeMode]), "resize"),
+                (keys(&[Action::LayoutMode]), "layout"),
+                (keys(&[Action::Kill]), "kill"),
+                (keys(&[Action::Help]), "all keys"),
+                (keys(&[Action::Quit]), "quit"),
             ];
+            let layout = state
+                .layout_target()
+                .map(|node| format!(" {{{:?}}}", node.layout))
+                .unwrap_or_default();
+            let follow = if state.follow_focus {
+                " [follow]"
+            } else if state.live_focus {
+                " [live]"
+            } else {
+                ""
+            };
+            let count = state
+                .count
+                .map(|count| format!(" {}", count))
+                .unwrap_or_default();
+            build_menu_span(format!("Select{}{}{}", layout, follow, count), actions)
+        }
+    };
+    Paragraph::new(menu_span).block(block)
+}
 
-            build_menu_span("Move", actions)
+/// Every binding grouped by mode
+fn build_help_widget(state: &State) -> Paragraph<'_> {
+    let heading = |mode: &'static str| {
+        Spans::from(Span::styled(
+            mode,
+            Style::default().add_modifier(Modifier::REVERSED),
+        ))
+    };
+    let binding = |keys: String, action: &'static str| {
+        Spans::from(vec![
+            Span::styled(
+                format!(" {:>12} ∷ ", keys),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(action),
+        ])
+    };
+
+    let mut lines = vec![heading("Select")];
+    lines.extend(Action::ALL.iter().map(|action| {
+        let keys = state
+            .keymap
+            .keys(*action)
+            .iter()
+            .map(|key| key_name(*key))
+            .collect::<Vec<_>>()
+            .join("/");
+        binding(keys, action.description())
+    }));
+    let fixed_modes = [
+        ("Move", MOVE_ACTIONS),
+        ("Resize", RESIZE_ACTIONS),
+        ("Gaps", GAPS_ACTIONS),
+        ("Layout", LAYOUT_ACTIONS),
+        ("Search", SEARCH_ACTIONS),
+        ("Command", COMMAND_ACTIONS),
+        ("Mark", MARK_ACTIONS),
+        ("Move to workspace", MOVE_TO_WORKSPACE_ACTIONS),
+        ("Jump", JUMP_ACTIONS),
+        ("Workspaces", WORKSPACES_ACTIONS),
+        ("Marks", MARKS_ACTIONS),
+        ("JSON", RAW_JSON_ACTIONS),
+        ("Confirm", CONFIRM_ACTIONS),
+    ];
+    for (mode, actions) in fixed_modes.iter() {
+        lines.push(Spans::default());
+        lines.push(heading(mode));
+        lines.extend(
+            actions
+                .iter()
+                .map(|(key, action)| binding(key.to_string(), action)),
+        );
+    }
+
+    Paragraph::new(lines).block(Block::default().title("Help").borders(Borders::ALL))
+}
+
+/// Area of the given percentage of `area`, centered in it
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_y) / 2),
+                Constraint::Percentage(percent_y),
+                Constraint::Percentage((100 - percent_y) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_x) / 2),
+                Constraint::Percentage(percent_x),
+                Constraint::Percentage((100 - percent_x) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(vertical[1])[1]
+}
+
+/// Status message, or the text being typed
+fn build_status_widget(state: &State) -> Paragraph<'_> {
+    match state.mode {
+        StateMode::Search(ref input) => input.render("/"),
+        StateMode::Command(ref input) => input.render(":"),
+        StateMode::MoveToWorkspace(_, ref input) => input.render("workspace number "),
+        StateMode::Mark(ref input) => {
+            let marks = state.selected_marks();
+            let current = if marks.is_empty() {
+                String::new()
+            } else {
+                format!("  (replacing {})", marks.join(", "))
+            };
+            let mut line = input.spans("mark ");
+            line.0.push(Span::raw(current));
+            Paragraph::new(line)
         }
-        StateMode::None => {
-            let actions = vec![("m", "move mode"), ("s", "toggle split"), ("q", "quit")];
-            build_menu_span("Select", actions)
+        _ => Paragraph::new(state.status.as_deref().unwrap_or_default()),
+    }
+}
+
+/// Wall clock time as `HH:MM:SS`, in the local timezone
+fn local_time() -> String {
+    let tm = unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm = std::mem::zeroed::<libc::tm>();
+        if libc::localtime_r(&now, &mut tm).is_null() {
+            return String::new();
         }
+        tm
     };
-    Paragraph::new(menu_span).block(block)
+    format!("{:02}:{:02}:{:02}", tm.tm_hour, tm.tm_min, tm.tm_sec)
+}
+
+/// Part of the tree in view when it is filtered, the binding mode, then the
+/// i3 event count and time when the clock is shown
+fn footer(view: Option<&str>, binding_mode: &str, clock: Option<(u64, &str)>) -> String {
+    let mut parts: Vec<String> = view.map(str::to_string).into_iter().collect();
+    parts.push(binding_mode.to_string());
+    if let Some((i3_event_count, time)) = clock {
+        let plural = if i3_event_count == 1 { "" } else { "s" };
+        parts.push(format!("{} i3 event{}", i3_event_count, plural));
+        parts.push(time.to_string());
+    }
+    format!(" {}", parts.join(" ┃ "))
+}
+
+/// What the view is restricted to
+fn view_label(state: &State) -> Option<String> {
+    let root = state.view_root();
+    let kind = match root.nodetype {
+        NodeType::Workspace => "workspace",
+        NodeType::Output => "output",
+        _ if state.compact => return Some("compact".to_string()),
+        _ => return None,
+    };
+    let compact = if state.compact { ", compact" } else { "" };
+    Some(format!(
+        "{} {}{}",
+        kind,
+        root.name.as_deref().unwrap_or_default(),
+        compact
+    ))
 }
 
 type IOBoundTerminal =
     Terminal<TermionBackend<AlternateScreen<MouseTerminal<RawTerminal<Stdout>>>>>;
 
-pub(crate) struct Renderer(IOBoundTerminal);
+pub(crate) struct Renderer {
+    terminal: IOBoundTerminal,
+    /// Keeps the tree scrolled so that the selection stays in view
+    tree_state: TableState,
+    /// Rows of the last frame, along with what they were built from
+    tree_rows: Option<(RowsKey, Vec<TreeRow>)>,
+    /// Tree pane of the last frame, borders included
+    tree_area: Rect,
+    /// Where the rows of the last frame were drawn, for mouse clicks
+    rows_area: Rect,
+    /// Index of the row drawn first, as `tree_state` scrolled it
+    rows_offset: usize,
+}
+
+/// Inside the borders of the tree pane, below the header
+fn tree_rows_area(tree: Rect) -> Rect {
+    Rect {
+        x: tree.x + 1,
+        y: tree.y + 2,
+        width: tree.width.saturating_sub(2),
+        height: tree.height.saturating_sub(3),
+    }
+}
+
+/// First row drawn, scrolling as `Table` does to keep `selected` in view
+fn table_offset(selected: Option<usize>, height: usize, offset: usize) -> usize {
+    match selected {
+        Some(selected) if selected + 1 >= height + offset => selected + 1 - height,
+        Some(selected) if selected < offset => selected,
+        Some(_) => offset,
+        None => 0,
+    }
+}
+
+/// Areas of the screen
+struct Panes {
+    menu: Rect,
+    breadcrumb: Rect,
+    tree: Rect,
+    /// Right of the tree, for the details or the workspaces
+    side: Option<Rect>,
+    status: Rect,
+}
+
+impl Panes {
+    fn new(area: Rect, show_side: bool) -> Self {
+        let split = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(3),
+                    Constraint::Length(1),
+                    Constraint::Min(0),
+                    Constraint::Length(1),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+        let (tree, side) = if show_side {
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+                .split(split[2]);
+            (columns[0], Some(columns[1]))
+        } else {
+            (split[2], None)
+        };
+        Self {
+            menu: split[0],
+            breadcrumb: split[1],
+            tree,
+            side,
+            status: split[3],
+        }
+    }
+}
+
+/// Everything the rows depend on, the selection is highlighted by `tree_state`
+type RowsKey = (u64, Option<String>, usize, bool, Option<String>);
+
+/// Leave the alternate screen and raw mode before the panic message is printed,
+/// otherwise it is lost and the shell is left unusable
+fn install_panic_hook() {
+    // Same descriptor as the one termion switches to raw mode
+    let cooked = unsafe {
+        let mut termios = std::mem::zeroed::<libc::termios>();
+        if libc::tcgetattr(libc::STDOUT_FILENO, &mut termios) != 0 {
+            return;
+        }
+        termios
+    };
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        unsafe { libc::tcsetattr(libc::STDOUT_FILENO, libc::TCSANOW, &cooked) };
+        let mut stdout = io::stdout();
+        let _ = write!(stdout, "{}{}", ToMainScreen, cursor::Show);
+        let _ = stdout.flush();
+        default_hook(info);
+    }));
+}
 
 impl Renderer {
     pub(crate) fn new() -> io::Result<Self> {
+        install_panic_hook();
         let stdout = io::stdout().into_raw_mode()?;
         let stdout = MouseTerminal::from(stdout);
         let stdout = AlternateScreen::from(stdout);
         let backend = TermionBackend::new(stdout);
         let terminal = Terminal::new(backend)?;
 
-        Ok(Self(terminal))
+        Ok(Self {
+            terminal,
+            tree_state: TableState::default(),
+            tree_rows: None,
+            tree_area: Rect::default(),
+            rows_area: Rect::default(),
+            rows_offset: 0,
+        })
+    }
+
+    /// Whether the 0 based `column` and `row` of the terminal were in the
+    /// tree pane in the last frame
+    pub(crate) fn in_tree(&self, column: u16, row: u16) -> bool {
+        let area = self.tree_area;
+        (area.left()..area.right()).contains(&column) && (area.top()..area.bottom()).contains(&row)
+    }
+
+    /// Index of the tree row drawn at the 0 based `column` and `row` of the
+    /// terminal in the last frame
+    pub(crate) fn row_at(&self, column: u16, row: u16) -> Option<usize> {
+        let area = self.rows_area;
+        let inside = (area.left()..area.right()).contains(&column)
+            && (area.top()..area.bottom()).contains(&row);
+        let index = self.rows_offset + usize::from(row.checked_sub(area.y)?);
+        let count = self.tree_rows.as_ref().map_or(0, |(_, rows)| rows.len());
+        Some(index).filter(|index| inside && *index < count)
     }
 
     pub(crate) fn render(&mut self, state: &State) -> Result<(), Box<dyn Error>> {
-        self.0.draw(|frame| {
-            let tree_items = node_into_ui_list(
-                &state.node_tree,
-                Context {
-                    selected_id: Some(state.selected),
-                    ..Context::default()
-                },
+        let tree_state = &mut self.tree_state;
+        tree_state.select(state.selected_index());
+        let picker = matches!(
+            state.mode,
+            StateMode::Workspaces(_) | StateMode::Marks(_) | StateMode::RawJson(_)
+        );
+        let mut panes = Panes::new(self.terminal.size()?, state.show_details || picker);
+        let time = local_time();
+        let clock = Some((state.i3_event_count, time.as_str())).filter(|_| state.show_clock);
+        let footer = footer(view_label(state).as_deref(), &state.binding_mode, clock);
+        let footer_width = (footer.width() as u16).min(panes.status.width);
+        let footer_area = Rect {
+            x: panes.status.right() - footer_width,
+            width: footer_width,
+            ..panes.status
+        };
+        panes.status.width -= footer_width;
+        // Inside the borders of the tree pane
+        let name_width = name_column_width(panes.tree.width.saturating_sub(2));
+        let widths = [
+            Constraint::Length(name_width),
+            Constraint::Length(TYPE_COLUMN_WIDTH),
+            Constraint::Length(LAYOUT_COLUMN_WIDTH),
+            Constraint::Length(SHARE_COLUMN_WIDTH),
+        ];
+        let key = (
+            state.tree_generation,
+            state.search_query().map(str::to_string),
+            usize::from(name_width),
+            state.show_class,
+            match state.mode {
+                StateMode::Jump(_, ref typed) => Some(typed.text().to_string()),
+                _ => None,
+            },
+        );
+        if self.tree_rows.as_ref().map(|(built, _)| built) != Some(&key) {
+            let view = TreeView::from(state);
+            let rows = node_into_ui_rows(
+                state.view_root(),
+                &view,
+                Context::new(view.glyphs),
+                usize::from(name_width),
             );
-            let tree_widget = build_tree_widget(tree_items);
+            self.tree_rows = Some((key, rows));
+        }
+        let tree_rows = self.tree_rows.as_ref().map_or(&[][..], |(_, rows)| rows);
+        let selected_color = state
+            .theme
+            .selected
+            .filter(|_| color_enabled() && !state.no_background);
+        self.tree_area = panes.tree;
+        let rows_area = tree_rows_area(panes.tree);
+        // `Table` assumes room for its borders and column spacing
+        let drawn = panes.tree.width > TREE_COLUMNS.len() as u16 + 1;
+        if drawn {
+            self.rows_area = rows_area;
+            if rows_area.height > 0 {
+                self.rows_offset = table_offset(
+                    tree_state.selected(),
+                    usize::from(rows_area.height),
+                    self.rows_offset,
+                );
+            }
+        } else {
+            self.rows_area = Rect::default();
+        }
+        let rows_offset = self.rows_offset;
+        self.terminal.draw(|frame| {
+            let tree_widget =
+                build_tree_widget(tree_rows, &widths, tree_state.selected(), selected_color);
             let menu_widget = build_menu_widget(state);
-            // Layout
-            let split = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
-                .split(frame.size());
-
-            frame.render_widget(menu_widget, split[0]);
-            frame.render_widget(tree_widget, split[1]);
+
+            frame.render_widget(menu_widget, panes.menu);
+            frame.render_widget(build_breadcrumb_widget(state), panes.breadcrumb);
+            if drawn {
+                frame.render_stateful_widget(tree_widget, panes.tree, tree_state);
+                let highlight = MatchHighlight {
+                    rows: tree_rows,
+                    offset: rows_offset,
+                };
+                frame.render_widget(highlight, rows_area);
+            }
+            if let Some(side) = panes.side {
+                match state.mode {
+                    StateMode::Workspaces(chosen) => {
+                        frame.render_widget(build_workspaces_widget(state, chosen), side)
+                    }
+                    StateMode::Marks(chosen) => {
+                        frame.render_widget(build_marks_widget(state, chosen), side)
+                    }
+                    StateMode::RawJson(scroll) => {
+                        frame.render_widget(build_raw_json_widget(state, scroll), side)
+                    }
+                    _ => frame.render_widget(build_details_widget(state), side),
+                }
+            }
+            frame.render_widget(build_status_widget(state), panes.status);
+            frame.render_widget(Paragraph::new(footer.as_str()), footer_area);
+
+            if let StateMode::Help = state.mode {
+                let popup = centered_rect(60, 80, frame.size());
+                frame.render_widget(Clear, popup);
+                frame.render_widget(build_help_widget(state), popup);
+            }
+            if let StateMode::Confirm(ref pending) = state.mode {
+                let popup = centered_rect(50, 20, frame.size());
+                frame.render_widget(Clear, popup);
+                frame.render_widget(
+                    build_confirm_widget(confirm_question(state, pending)),
+                    popup,
+                );
+            }
         })?;
         Ok(())
     }
 }
+
+/// The wrapped stdout restores the terminal when dropped, the panic hook is no
+/// longer needed past this point
+impl Drop for Renderer {
+    fn drop(&mut self) {
+        let _ = panic::take_hook();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::{balanced_tree, node};
+    use i3ipc::reply::NodeLayout;
+
+    /// Rows of `tree` outside of any search
+    fn ui_nodes(tree: &Node) -> Vec<UiNode> {
+        ui_nodes_with(tree, &UNICODE_GLYPHS)
+    }
+
+    fn ui_nodes_with(tree: &Node, glyphs: &'static Glyphs) -> Vec<UiNode> {
+        let (extras, collapsed, theme) = (NodeExtras::new(), HashSet::new(), Theme::default());
+        let view = TreeView {
+            output_headers: true,
+            glyphs,
+            ..TreeView::plain(&extras, &collapsed, &theme)
+        };
+        node_into_ui_nodes(tree, &view, Context::new(glyphs))
+    }
+
+    /// Rows as `--print-tree` writes them
+    fn rendered(tree: &Node) -> Vec<String> {
+        ui_nodes(tree).iter().map(UiNode::to_string).collect()
+    }
+
+    #[test]
+    fn renders_a_root_with_one_child() {
+        assert_eq!(
+            rendered(&node(1, vec![node(2, vec![])])),
+            vec!["[Con] {SplitH} - node 1", "└──[Con] {SplitH} - node 2"]
+        );
+    }
+
+    #[test]
+    fn renders_siblings() {
+        let tree = node(1, vec![node(2, vec![]), node(3, vec![]), node(4, vec![])]);
+        assert_eq!(
+            rendered(&tree),
+            vec![
+                "[Con] {SplitH} - node 1",
+                "├──[Con] {SplitH} - node 2",
+                "├──[Con] {SplitH} - node 3",
+                "└──[Con] {SplitH} - node 4",
+            ]
+        );
+    }
+
+    #[test]
+    fn renders_nested_branches() {
+        let tree = node(
+            1,
+            vec![
+                node(2, vec![node(3, vec![]), node(4, vec![node(5, vec![])])]),
+                node(6, vec![node(7, vec![])]),
+            ],
+        );
+        assert_eq!(
+            rendered(&tree),
+            vec![
+                "[Con] {SplitH} - node 1",
+                "├──[Con] {SplitH} - node 2",
+                "│  ├──[Con] {SplitH} - node 3",
+                "│  └──[Con] {SplitH} - node 4",
+                "│     └──[Con] {SplitH} - node 5",
+                "└──[Con] {SplitH} - node 6",
+                "   └──[Con] {SplitH} - node 7",
+            ]
+        );
+    }
+
+    #[test]
+    fn last_branches_keep_their_guides_below() {
+        let tree = node(
+            1,
+            vec![
+                node(2, vec![node(3, vec![])]),
+                node(
+                    4,
+                    vec![
+                        node(5, vec![node(6, vec![])]),
+                        node(7, vec![node(8, vec![])]),
+                    ],
+                ),
+            ],
+        );
+        assert_eq!(
+            rendered(&tree),
+            vec![
+                "[Con] {SplitH} - node 1",
+                "├──[Con] {SplitH} - node 2",
+                "│  └──[Con] {SplitH} - node 3",
+                "└──[Con] {SplitH} - node 4",
+                "   ├──[Con] {SplitH} - node 5",
+                "   │  └──[Con] {SplitH} - node 6",
+                "   └──[Con] {SplitH} - node 7",
+                "      └──[Con] {SplitH} - node 8",
+            ]
+        );
+    }
+
+    #[test]
+    fn flattens_wide_trees_in_order() {
+        let rows = ui_nodes(&balanced_tree(501, 500));
+        assert_eq!(rows.len(), 501);
+        assert_eq!(rows[1].indentation, UNICODE_GLYPHS.branch);
+        assert_eq!(rows[500].indentation, UNICODE_GLYPHS.leaf);
+        assert_eq!(rows[500].name, "node 501");
+    }
+
+    #[test]
+    fn no_color_keeps_modifiers_only() {
+        let mut window = node(2, vec![]);
+        window.focused = true;
+        let mut workspace = node(1, vec![window]);
+        workspace.nodetype = NodeType::Workspace;
+        let rows: Vec<UiNode> = ui_nodes(&workspace)
+            .into_iter()
+            .map(|row| UiNode {
+                color: false,
+                ..row
+            })
+            .collect();
+        assert_eq!(ui_node_style(&rows[0], &Theme::default()), Style::default());
+        let focused = ui_node_style(&rows[1], &Theme::default());
+        assert_eq!((focused.fg, focused.bg), (None, None));
+        assert!(focused
+            .add_modifier
+            .contains(Modifier::BOLD | Modifier::UNDERLINED));
+    }
+
+    #[test]
+    fn highlights_layer_over_the_type_style() {
+        let mut window = node(2, vec![]);
+        window.focused = true;
+        let tree = node(1, vec![window]);
+        let rows = ui_nodes(&tree);
+        assert_eq!(
+            ui_node_style(&rows[0], &Theme::default()),
+            Style::default().add_modifier(Modifier::DIM)
+        );
+        let focused = ui_node_style(&rows[1], &Theme::default());
+        assert_eq!(focused.bg, Some(Color::LightGreen));
+        assert!(focused.add_modifier.contains(Modifier::BOLD));
+    }
+
+    #[test]
+    fn without_background_highlights_color_the_text() {
+        let mut window = node(2, vec![]);
+        window.focused = true;
+        let mut urgent = node(3, vec![]);
+        urgent.urgent = true;
+        let rows: Vec<UiNode> = ui_nodes(&node(1, vec![window, urgent]))
+            .into_iter()
+            .map(|row| UiNode {
+                background: false,
+                ..row
+            })
+            .collect();
+        let focused = ui_node_style(&rows[1], &Theme::default());
+        assert_eq!((focused.fg, focused.bg), (Some(Color::LightGreen), None));
+        assert!(focused.add_modifier.contains(Modifier::UNDERLINED));
+        let urgent = ui_node_style(&rows[2], &Theme::default());
+        assert_eq!((urgent.fg, urgent.bg), (Some(Color::LightMagenta), None));
+    }
+
+    #[test]
+    fn percent_bars() {
+        assert_eq!(percent_bar(0.62, &UNICODE_GLYPHS), "[███▊  ] 62%");
+        assert_eq!(percent_bar(0.5, &UNICODE_GLYPHS), "[███   ] 50%");
+        assert_eq!(percent_bar(1.0, &UNICODE_GLYPHS), "[██████] 100%");
+        assert_eq!(percent_bar(0.01, &UNICODE_GLYPHS), "[      ] 1%");
+        assert_eq!(percent_bar(0.62, &ASCII_GLYPHS), "[####  ] 62%");
+    }
+
+    #[test]
+    fn floating_nodes_hang_from_dashed_glyphs() {
+        let mut tree = node(1, vec![node(2, vec![])]);
+        let mut floating = node(3, vec![node(4, vec![])]);
+        floating.nodetype = NodeType::FloatingCon;
+        tree.floating_nodes = vec![floating];
+        let rows = ui_nodes(&tree);
+        let indentation: Vec<&str> = rows.iter().map(|row| row.indentation.as_str()).collect();
+        assert_eq!(indentation, vec!["", "├──", "└╌╌", "   └──"]);
+        assert!(!rows[1].floating);
+        assert!(rows[2].floating && rows[3].floating);
+    }
+
+    #[test]
+    fn rows_follow_the_navigation_order() {
+        let mut tree = node(1, vec![node(2, vec![node(3, vec![])])]);
+        tree.floating_nodes = vec![node(4, vec![node(5, vec![])]), node(6, vec![])];
+        let row_ids: Vec<NodeId> = ui_nodes(&tree).iter().map(|row| row.con_id).collect();
+        assert_eq!(row_ids, crate::collect_ids(&tree, &HashSet::new()));
+    }
+
+    #[test]
+    fn outputs_render_as_headers() {
+        let mut output = node(2, vec![node(3, vec![])]);
+        output.nodetype = NodeType::Output;
+        output.name = Some("eDP-1".to_string());
+        output.rect = (0, 0, 1920, 1080);
+        let rows = ui_nodes(&node(1, vec![output]));
+        assert_eq!(rows[1].to_string(), "└──━━ eDP-1 1920x1080 ━━");
+        assert!(!rows[2].header);
+    }
+
+    #[test]
+    fn long_names_are_cut_first() {
+        let mut window = node(2, vec![]);
+        window.name = Some("ééééééééééééééééééééé".to_string());
+        let rows = ui_nodes(&node(1, vec![window]));
+        assert_eq!(rows[1].name_cell(100), "└──ééééééééééééééééééééé");
+        let cell = rows[1].name_cell(8);
+        assert_eq!(cell, "└──éééé…");
+        assert_eq!(cell.chars().count(), 8);
+        assert_eq!(rows[1].name_cell(2), "└…");
+    }
+
+    #[test]
+    fn cells_line_up_in_columns() {
+        let mut window = node(2, vec![]);
+        window.percent = Some(0.5);
+        let rows = ui_nodes(&node(1, vec![window]));
+        assert_eq!(
+            rows[1].cells(20),
+            [
+                "└──node 2".to_string(),
+                "Con".to_string(),
+                "SplitH".to_string(),
+                "[███   ] 50%".to_string()
+            ]
+        );
+        assert_eq!(name_column_width(80), 80 - 11 - 8 - 13 - 3);
+    }
+
+    #[test]
+    fn offsets_scroll_as_the_table_does() {
+        assert_eq!(table_offset(Some(3), 10, 0), 0);
+        assert_eq!(table_offset(Some(9), 10, 0), 0);
+        assert_eq!(table_offset(Some(12), 10, 0), 3);
+        assert_eq!(table_offset(Some(5), 10, 3), 3);
+        assert_eq!(table_offset(Some(1), 10, 3), 1);
+        assert_eq!(table_offset(None, 10, 3), 0);
+    }
+
+    #[test]
+    fn wide_characters_take_two_columns() {
+        assert_eq!(truncate("日本語のタイトル", 6), "日本…");
+        assert_eq!(truncate("日本語のタイトル", 7), "日本語…");
+        assert_eq!(truncate("🦀🦀🦀", 6), "🦀🦀🦀");
+        assert_eq!(truncate("🦀🦀🦀", 5), "🦀🦀…");
+        assert_eq!(truncate("│  ├──abc", 6), "│  ├─…");
+    }
+
+    #[test]
+    fn both_glyph_sets_draw_the_same_tree() {
+        let mut tree = node(1, vec![node(2, vec![node(3, vec![])]), node(4, vec![])]);
+        tree.floating_nodes = vec![node(5, vec![])];
+        let lines = |glyphs| -> Vec<String> {
+            ui_nodes_with(&tree, glyphs)
+                .iter()
+                .map(|row| row.name_cell(80))
+                .collect()
+        };
+        assert_eq!(
+            lines(&UNICODE_GLYPHS),
+            vec![
+                "▭ node 1",
+                "├──▭ node 2",
+                "│  └──node 3",
+                "├──node 4",
+                "└╌╌node 5"
+            ]
+        );
+        assert_eq!(
+            lines(&ASCII_GLYPHS),
+            vec![
+                "H node 1",
+                "|--H node 2",
+                "|  `--node 3",
+                "|--node 4",
+                "`..node 5"
+            ]
+        );
+    }
+
+    #[test]
+    fn breadcrumb_names_the_ancestors() {
+        let mut output = node(2, vec![node(3, vec![node(4, vec![])])]);
+        output.name = Some("eDP-1".to_string());
+        let mut tree = node(1, vec![output]);
+        if let Some(split) = tree.nodes[0].nodes.get_mut(0) {
+            split.name = None;
+        }
+        assert_eq!(breadcrumb(&tree, 4), "eDP-1 > Con {SplitH} > node 4");
+        assert_eq!(breadcrumb(&tree, 1), "");
+    }
+
+    #[test]
+    fn details_describe_the_window() {
+        let mut window = node(2, vec![]);
+        window.rect = (10, 20, 800, 600);
+        window.window_properties = Some(
+            vec![(WindowProperty::Class, "Firefox".to_string())]
+                .into_iter()
+                .collect(),
+        );
+        let extra = NodeExtra {
+            fullscreen_mode: 1,
+            marks: vec!["web".to_string()],
+            app_id: None,
+            gaps: None,
+            pid: None,
+        };
+        let lines = details_lines(&window, Some(&extra), true, Some(1));
+        assert!(lines.contains(&"class: Firefox".to_string()));
+        assert!(lines.contains(&"instance: ".to_string()));
+        assert!(lines.contains(&"marks: web".to_string()));
+        assert!(lines.contains(&"rect: 800x600 at 10,20".to_string()));
+        assert!(lines.contains(&"fullscreen mode: 1".to_string()));
+        assert!(lines.contains(&"floating: true".to_string()));
+        assert!(lines.contains(&"border: normal".to_string()));
+        assert!(lines.iter().any(|line| line.starts_with("pid: 1")));
+    }
+
+    #[test]
+    fn windows_can_be_labelled_by_class() {
+        let mut window = node(2, vec![]);
+        window.window_properties = Some(
+            vec![(WindowProperty::Class, "URxvt".to_string())]
+                .into_iter()
+                .collect(),
+        );
+        let mut wayland = node(3, vec![]);
+        wayland.window_properties = None;
+        let mut extras = NodeExtras::new();
+        extras.insert(
+            3,
+            NodeExtra {
+                app_id: Some("foot".to_string()),
+                ..NodeExtra::default()
+            },
+        );
+        let (collapsed, theme) = (HashSet::new(), Theme::default());
+        let view = TreeView {
+            show_class: true,
+            ..TreeView::plain(&extras, &collapsed, &theme)
+        };
+        let rows = node_into_ui_nodes(
+            &node(1, vec![window, wayland]),
+            &view,
+            Context::new(&UNICODE_GLYPHS),
+        );
+        let labels: Vec<&str> = rows.iter().map(UiNode::label).collect();
+        assert_eq!(labels, vec!["node 1", "URxvt", "foot"]);
+        assert_eq!(rows[0].class, "");
+    }
+
+    #[test]
+    fn footer_counts_i3_events() {
+        assert_eq!(
+            footer(None, "default", Some((0, "12:30:00"))),
+            " default ┃ 0 i3 events ┃ 12:30:00"
+        );
+        assert_eq!(
+            footer(None, "resize", Some((1, "12:30:00"))),
+            " resize ┃ 1 i3 event ┃ 12:30:00"
+        );
+        assert_eq!(footer(None, "default", None), " default");
+        assert_eq!(
+            footer(Some("output HDMI-1"), "default", None),
+            " output HDMI-1 ┃ default"
+        );
+    }
+
+    #[test]
+    fn local_time_is_formatted() {
+        let time = local_time();
+        assert_eq!(time.len(), 8);
+        assert_eq!(time.matches(':').count(), 2);
+    }
+
+    #[test]
+    fn collapsed_nodes_hide_their_children() {
+        let extras = NodeExtras::new();
+        let collapsed = vec![2].into_iter().collect();
+        let theme = Theme::default();
+        let view = TreeView::plain(&extras, &collapsed, &theme);
+        let tree = node(1, vec![node(2, vec![node(3, vec![])]), node(4, vec![])]);
+        let rows = node_into_ui_nodes(&tree, &view, Context::new(&UNICODE_GLYPHS));
+        let ids: Vec<i64> = rows.iter().map(|row| row.con_id).collect();
+        assert_eq!(ids, vec![1, 2, 4]);
+        assert_eq!(rows[1].lead(), "├──▸ ▭ ");
+        assert_eq!(rows[2].lead(), "└──");
+    }
+
+    #[test]
+    fn containers_lead_with_their_layout() {
+        let mut tabbed = node(2, vec![node(3, vec![])]);
+        tabbed.layout = NodeLayout::Tabbed;
+        let mut stacked = node(4, vec![node(5, vec![])]);
+        stacked.layout = NodeLayout::Stacked;
+        let mut vertical = node(1, vec![tabbed, stacked]);
+        vertical.layout = NodeLayout::SplitV;
+        let icons: Vec<Option<&str>> = ui_nodes(&vertical)
+            .iter()
+            .map(|row| row.layout_icon())
+            .collect();
+        assert_eq!(icons, vec![Some("▯"), Some("▤"), None, Some("▥"), None]);
+    }
+
+    #[test]
+    fn scratchpad_is_labelled() {
+        let mut scratchpad = node(2, vec![]);
+        scratchpad.nodetype = NodeType::Workspace;
+        scratchpad.name = Some("__i3_scratch".to_string());
+        assert_eq!(ui_nodes(&scratchpad)[0].name, "Scratchpad (empty)");
+        scratchpad.floating_nodes = vec![node(3, vec![]), node(4, vec![])];
+        let rows = ui_nodes(&scratchpad);
+        assert_eq!(rows[0].name, "Scratchpad (2 hidden windows)");
+        assert_eq!(rows[1].name, "node 3");
+    }
+
+    #[test]
+    fn jump_labels_lead_the_rows() {
+        let (extras, collapsed, theme) = (NodeExtras::new(), HashSet::new(), Theme::default());
+        let view = TreeView {
+            jump_labels: vec![(1, "sa"), (2, "as")].into_iter().collect(),
+            jump_typed: Some("a"),
+            ..TreeView::plain(&extras, &collapsed, &theme)
+        };
+        let rows = node_into_ui_nodes(
+            &node(1, vec![node(2, vec![])]),
+            &view,
+            Context::new(&UNICODE_GLYPHS),
+        );
+        assert_eq!(rows[0].lead(), "sa ▭ ");
+        assert_eq!(rows[1].lead(), "as └──");
+        assert!(rows[0].jump_missed);
+        assert!(!rows[1].jump_missed);
+    }
+
+    #[test]
+    fn workspace_lines_flag_focus_visibility_and_urgency() {
+        let workspace = Workspace {
+            num: 3,
+            name: "3: mail".to_string(),
+            visible: true,
+            focused: false,
+            urgent: true,
+            rect: (0, 0, 0, 0),
+            output: "HDMI-1".to_string(),
+        };
+        assert_eq!(workspace_line(&workspace), " +! 3: mail  HDMI-1");
+    }
+
+    #[test]
+    fn matches_are_found_whatever_the_case() {
+        assert_eq!(match_ranges("Terminal term", "TERM"), vec![0..4, 9..13]);
+        assert_eq!(match_ranges("İx", "x"), vec![2..3]);
+        assert!(match_ranges("Terminal", "").is_empty());
+    }
+
+    #[test]
+    fn only_the_names_of_matching_rows_are_underlined() {
+        let mut term = node(2, vec![]);
+        term.name = Some("term".to_string());
+        let mut extras = NodeExtras::new();
+        // A mark matching the query isn't what the search matched
+        extras.insert(
+            3,
+            NodeExtra {
+                marks: vec!["term".to_string()],
+                ..NodeExtra::default()
+            },
+        );
+        let (collapsed, theme) = (HashSet::new(), Theme::default());
+        let view = TreeView {
+            query: Some("TE"),
+            ..TreeView::plain(&extras, &collapsed, &theme)
+        };
+        let tree = node(1, vec![term, node(3, vec![])]);
+        let columns: Vec<Vec<Range<u16>>> =
+            node_into_ui_nodes(&tree, &view, Context::new(&UNICODE_GLYPHS))
+                .iter()
+                .map(|row| row.matched_columns(40))
+                .collect();
+        // `├──` leads the name of the first child
+        assert_eq!(columns, vec![vec![], vec![3..5], vec![]]);
+    }
+
+    #[test]
+    fn counts_follow_containers_only() {
+        let tree = node(1, vec![node(2, vec![node(3, vec![])]), node(4, vec![])]);
+        let suffixes: Vec<String> = ui_nodes(&tree)
+            .into_iter()
+            .map(|ui_node| UiNode {
+                show_counts: true,
+                ..ui_node
+            })
+            .map(|ui_node| ui_node.suffix())
+            .collect();
+        assert_eq!(
+            suffixes,
+            vec![" (2 children, depth 0)", " (1 child, depth 1)", "", ""]
+        );
+    }
+}